@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use shared_runtime::retry::RetryConfig;
 use std::fs;
 
 /// Configuration for the indexing worker
@@ -6,9 +7,18 @@ use std::fs;
 pub struct Config {
     pub kafka_broker: String,
     pub input_topic: String,
+    pub dead_letter_topic: String,
+    pub status_topic: String,
     pub consumer_group: String,
+    pub kafka_timeout_ms: String,
+    pub kafka_send_timeout_secs: u64,
     pub qdrant_grpc_url: String,
     pub collection_name: String,
+    pub collection_model_id: String,
+    /// Named sparse vector of the collection that receives BM25 chunk vectors
+    pub sparse_vector_name: String,
+    pub retry: RetryConfig,
+    pub shutdown_timeout_secs: u64,
 }
 
 impl Config {
@@ -24,9 +34,17 @@ impl Config {
         Self {
             kafka_broker: "localhost:9092".to_string(),
             input_topic: "resume_vectorized".to_string(),
+            dead_letter_topic: "resume_vectorized_dlq".to_string(),
+            status_topic: "document_status".to_string(),
             consumer_group: "indexer_group_v1".to_string(),
+            kafka_timeout_ms: "5000".to_string(),
+            kafka_send_timeout_secs: 5,
             qdrant_grpc_url: "http://localhost:6334".to_string(),
             collection_name: "resumes".to_string(),
+            collection_model_id: "sentence-transformers/all-MiniLM-L6-v2".to_string(),
+            sparse_vector_name: "bm25".to_string(),
+            retry: RetryConfig::default(),
+            shutdown_timeout_secs: 30,
         }
     }
 }
@@ -0,0 +1,19 @@
+pub use shared_runtime::dlq::dead_letter;
+
+/// Pipeline stage at which a message failed, recorded in the `dlq.stage` header
+#[derive(Debug, Clone, Copy)]
+pub enum Stage {
+    Deserialize,
+    Index,
+    Delete,
+}
+
+impl Stage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Deserialize => "deserialize",
+            Stage::Index => "index",
+            Stage::Delete => "delete",
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use shared_runtime::retry::Retryable;
 use std::fmt;
 
 /// Custom error types for the indexing worker
@@ -5,6 +6,8 @@ use std::fmt;
 pub enum IndexerError {
     QdrantConnectionError(String),
     QdrantUpsertError(String),
+    QdrantDeleteError(String),
+    ModelMismatch(String),
     KafkaError(String),
     SerializationError(String),
 }
@@ -14,6 +17,8 @@ impl fmt::Display for IndexerError {
         match self {
             IndexerError::QdrantConnectionError(e) => write!(f, "Qdrant connection error: {}", e),
             IndexerError::QdrantUpsertError(e) => write!(f, "Qdrant upsert error: {}", e),
+            IndexerError::QdrantDeleteError(e) => write!(f, "Qdrant delete error: {}", e),
+            IndexerError::ModelMismatch(e) => write!(f, "Model mismatch: {}", e),
             IndexerError::KafkaError(e) => write!(f, "Kafka error: {}", e),
             IndexerError::SerializationError(e) => write!(f, "Serialization error: {}", e),
         }
@@ -22,6 +27,19 @@ impl fmt::Display for IndexerError {
 
 impl std::error::Error for IndexerError {}
 
+impl Retryable for IndexerError {
+    /// Qdrant and broker outages are transient; bad events and model mismatches are not
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            IndexerError::QdrantConnectionError(_)
+                | IndexerError::QdrantUpsertError(_)
+                | IndexerError::QdrantDeleteError(_)
+                | IndexerError::KafkaError(_)
+        )
+    }
+}
+
 impl From<rdkafka::error::KafkaError> for IndexerError {
     fn from(err: rdkafka::error::KafkaError) -> Self {
         IndexerError::KafkaError(err.to_string())
@@ -1,39 +1,280 @@
 use crate::config::Config;
 use crate::error::IndexerError;
 use qdrant_client::Payload;
-use qdrant_client::qdrant::{PointStruct, UpsertPointsBuilder};
+use qdrant_client::qdrant::value::Kind;
+use qdrant_client::qdrant::{
+    Condition, DeletePointsBuilder, Filter, NamedVectors, PayloadIncludeSelector, PointStruct,
+    Range, ScrollPointsBuilder, UpsertPointsBuilder, Vector, Vectors,
+};
 use shared_types::DocumentVectorizedEvent;
+use uuid::Uuid;
+
+/// Whether the event carries the last chunk of its document
+///
+/// An event too large for one Kafka message is split into consecutive parts;
+/// only the last completes the document.
+pub fn completes_document(event: &DocumentVectorizedEvent) -> bool {
+    event
+        .chunks
+        .last()
+        .is_some_and(|chunk| chunk.index + 1 >= event.chunk_count)
+}
 
 /// Process a single vectorized document event
 ///
-/// 1. Converts the document UUID to a Qdrant point ID
-/// 2. Constructs a Qdrant point with the embedding vector
-/// 3. Upserts the point into the configured collection
+/// 1. Refuses vectors produced by a model other than the collection's
+/// 2. Skips the event if a newer version of the document is already indexed,
+///    or if the document was deleted at the same or a newer version
+/// 3. Builds one Qdrant point per chunk of the event, carrying the parent document id,
+///    chunk offsets and chunk text, plus the chunk's BM25 sparse vector when the vector
+///    worker produced one
+/// 4. Upserts the points into the configured collection
+/// 5. Deletes chunks left over from a previous, longer version of the document,
+///    the single point it had before documents were chunked, and the marker
+///    of an older deletion
 pub async fn process_vector_event(
-    event: DocumentVectorizedEvent,
+    event: &DocumentVectorizedEvent,
     qdrant_client: &qdrant_client::Qdrant,
     config: &Config,
 ) -> Result<(), IndexerError> {
     let doc_id = event.id;
+    // The whole document's count; a split event carries only some of its chunks
+    let chunk_count = event.chunk_count;
+    let version = event.version;
+
+    tracing::info!(%doc_id, chunk_count, version, "Processing vectors for indexing");
+
+    // Vectors from different models live in different spaces and must never be mixed
+    if event.model_id != config.collection_model_id {
+        return Err(IndexerError::ModelMismatch(format!(
+            "vector produced by '{}' but collection '{}' expects '{}'",
+            event.model_id, config.collection_name, config.collection_model_id
+        )));
+    }
 
-    tracing::info!(%doc_id, "Processing vector for indexing");
+    if event.chunks.is_empty() {
+        return Err(IndexerError::SerializationError(
+            "vectorized event carries no chunks".to_string(),
+        ));
+    }
 
-    // Use the UUID string as the Qdrant point ID so it can be recovered
-    // losslessly in the matching-service (as_u128() as u64 truncates 128→64 bits)
-    let point = PointStruct::new(
-        doc_id.to_string(),
-        event.vector,
-        Payload::new(),
+    // Vectorization latency varies, so an older version can arrive after a
+    // newer one or after the document was deleted. Events of a document share
+    // a partition and are indexed one at a time, so nothing can change the
+    // stored state in between.
+    match indexed_state(doc_id, qdrant_client, config).await? {
+        Some(Indexed::Version(indexed)) if indexed > version => {
+            tracing::info!(%doc_id, version, indexed, "Skipping outdated document version");
+            return Ok(());
+        }
+        Some(Indexed::Deleted(deleted)) if deleted >= version => {
+            tracing::info!(%doc_id, version, deleted, "Skipping document version deleted since");
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    // Document metadata becomes the point payload so it can be filtered on at search time
+    let mut base_payload = Payload::try_from(serde_json::to_value(&event.metadata)?)
+        .map_err(|e| IndexerError::SerializationError(e.to_string()))?;
+    base_payload.insert("model_id", event.model_id.clone());
+    base_payload.insert("document_id", doc_id.to_string());
+    base_payload.insert(
+        "version",
+        i64::try_from(version).map_err(|e| IndexerError::SerializationError(e.to_string()))?,
     );
+    if let Some(fingerprint) = &event.fingerprint {
+        base_payload.insert("content_hash", fingerprint.content_hash.clone());
+        base_payload.insert("simhash", format!("{:016x}", fingerprint.simhash));
+    }
+    if let Some(duplicate) = &event.duplicate_of {
+        base_payload.insert("duplicate_of", duplicate.id.to_string());
+        base_payload.insert("duplicate_kind", duplicate.kind.as_str());
+    }
+
+    let points: Vec<PointStruct> = event
+        .chunks
+        .iter()
+        .map(|chunk| {
+            let mut payload = base_payload.clone();
+            payload.insert("chunk_index", chunk.index as i64);
+            payload.insert("chunk_count", chunk_count as i64);
+            payload.insert("chunk_start", chunk.start as i64);
+            payload.insert("chunk_end", chunk.end as i64);
+            // Lets the matching-service rerank results without another store
+            if let Some(text) = &chunk.text {
+                payload.insert("chunk_text", text.clone());
+            }
+
+            // UUID strings are recovered losslessly in the matching-service
+            // (as_u128() as u64 would truncate 128→64 bits)
+            let vectors: Vectors = match &chunk.sparse {
+                // The dense vector stays the collection's default (unnamed) vector
+                Some(sparse) => NamedVectors::default()
+                    .add_vector("", Vector::new_dense(chunk.vector.clone()))
+                    .add_vector(
+                        &config.sparse_vector_name,
+                        Vector::new_sparse(sparse.indices.clone(), sparse.values.clone()),
+                    )
+                    .into(),
+                None => chunk.vector.clone().into(),
+            };
+
+            PointStruct::new(
+                chunk_point_id(doc_id, chunk.index).to_string(),
+                vectors,
+                payload,
+            )
+        })
+        .collect();
+
+    qdrant_client
+        .upsert_points(UpsertPointsBuilder::new(&config.collection_name, points).wait(true))
+        .await
+        .map_err(|e| IndexerError::QdrantUpsertError(e.to_string()))?;
 
+    // A re-submitted document may now have fewer chunks than before, and a
+    // document indexed before chunking or deleted since has a point whose id
+    // is the document id
+    let stale_chunks = Filter::should([
+        Filter::must([
+            Condition::matches("document_id", doc_id.to_string()),
+            Condition::range(
+                "chunk_index",
+                Range {
+                    gte: Some(chunk_count as f64),
+                    ..Default::default()
+                },
+            ),
+        ])
+        .into(),
+        Condition::has_id([doc_id.to_string()]),
+    ]);
     qdrant_client
-        .upsert_points(
-            UpsertPointsBuilder::new(&config.collection_name, vec![point]).wait(true),
+        .delete_points(
+            DeletePointsBuilder::new(&config.collection_name)
+                .points(stale_chunks)
+                .wait(true),
         )
         .await
         .map_err(|e| IndexerError::QdrantUpsertError(e.to_string()))?;
 
-    tracing::info!(%doc_id, "Successfully indexed in Qdrant");
+    tracing::info!(%doc_id, chunk_count, "Successfully indexed in Qdrant");
+
+    Ok(())
+}
+
+/// What the collection holds for a document
+#[derive(Debug, Clone, Copy)]
+enum Indexed {
+    /// Chunks of this version of the document
+    Version(u64),
+    /// The marker left by a deletion of this version
+    Deleted(u64),
+}
+
+/// State of a document in the collection, if it was ever indexed or deleted
+///
+/// Points indexed before versioning carry no version and count as version 0.
+/// A document's chunks and its deletion marker never coexist.
+async fn indexed_state(
+    doc_id: Uuid,
+    qdrant_client: &qdrant_client::Qdrant,
+    config: &Config,
+) -> Result<Option<Indexed>, IndexerError> {
+    let response = qdrant_client
+        .scroll(
+            ScrollPointsBuilder::new(&config.collection_name)
+                .filter(Filter::should([
+                    Condition::matches("document_id", doc_id.to_string()),
+                    Condition::has_id([doc_id.to_string()]),
+                ]))
+                .limit(1)
+                .with_payload(PayloadIncludeSelector::new(vec![
+                    "version".to_string(),
+                    "deleted_version".to_string(),
+                ])),
+        )
+        .await
+        .map_err(|e| IndexerError::QdrantConnectionError(e.to_string()))?;
+
+    Ok(response.result.first().map(|point| {
+        let field = |name: &str| match point.payload.get(name).and_then(|v| v.kind.as_ref()) {
+            Some(Kind::IntegerValue(v)) => Some(u64::try_from(*v).unwrap_or_default()),
+            _ => None,
+        };
+        match field("deleted_version") {
+            Some(deleted) => Indexed::Deleted(deleted),
+            None => Indexed::Version(field("version").unwrap_or_default()),
+        }
+    }))
+}
+
+/// Delete every point of a document and remember the deletion
+///
+/// Removes all of its chunks, plus the single point a document had before
+/// chunking, whose id was the document id itself. A versioned deletion then
+/// leaves a marker point under the document id: it has no vectors, so no
+/// search returns it, and its `deleted_version` payload makes the worker
+/// refuse any later-arriving event that is not newer than the deletion.
+/// A deletion older than the indexed version is skipped. Tombstones without
+/// a version, from before deletions were versioned, leave no marker.
+pub async fn process_deletion(
+    doc_id: Uuid,
+    version: Option<u64>,
+    qdrant_client: &qdrant_client::Qdrant,
+    config: &Config,
+) -> Result<(), IndexerError> {
+    tracing::info!(%doc_id, ?version, "Deleting document from Qdrant");
+
+    if let Some(version) = version {
+        match indexed_state(doc_id, qdrant_client, config).await? {
+            Some(Indexed::Version(indexed)) if indexed > version => {
+                tracing::info!(%doc_id, version, indexed, "Skipping deletion of an older version");
+                return Ok(());
+            }
+            Some(Indexed::Deleted(deleted)) if deleted >= version => {
+                tracing::info!(%doc_id, version, deleted, "Document already deleted");
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
+    let document_points = Filter::should([
+        Condition::matches("document_id", doc_id.to_string()),
+        Condition::has_id([doc_id.to_string()]),
+    ]);
+    qdrant_client
+        .delete_points(
+            DeletePointsBuilder::new(&config.collection_name)
+                .points(document_points)
+                .wait(true),
+        )
+        .await
+        .map_err(|e| IndexerError::QdrantDeleteError(e.to_string()))?;
+
+    if let Some(version) = version {
+        let mut payload = Payload::new();
+        payload.insert(
+            "deleted_version",
+            i64::try_from(version).map_err(|e| IndexerError::SerializationError(e.to_string()))?,
+        );
+        let marker = PointStruct::new(doc_id.to_string(), NamedVectors::default(), payload);
+        qdrant_client
+            .upsert_points(
+                UpsertPointsBuilder::new(&config.collection_name, vec![marker]).wait(true),
+            )
+            .await
+            .map_err(|e| IndexerError::QdrantDeleteError(e.to_string()))?;
+    }
+
+    tracing::info!(%doc_id, "Successfully deleted from Qdrant");
 
     Ok(())
 }
+
+/// Deterministic point id of a chunk, so re-indexing a document overwrites its points
+fn chunk_point_id(doc_id: Uuid, chunk_index: usize) -> Uuid {
+    Uuid::new_v5(&doc_id, format!("chunk-{}", chunk_index).as_bytes())
+}
@@ -2,15 +2,44 @@ use crate::config::Config;
 use crate::error::IndexerError;
 use rdkafka::config::ClientConfig;
 use rdkafka::consumer::StreamConsumer;
+use rdkafka::producer::FutureProducer;
+use shared_runtime::retry::{DelayedRetryConfig, Relay};
+use std::time::Duration;
 
 /// Create a Kafka consumer configured for the indexing worker
-pub fn create_consumer(config: &Config) -> Result<StreamConsumer, IndexerError> {
+///
+/// Auto-commit is disabled: each message's offset is committed once it has
+/// been indexed, dead-lettered or scheduled for retry, for at-least-once delivery.
+pub fn create_consumer(config: &Config, group_id: &str) -> Result<StreamConsumer, IndexerError> {
     let consumer: StreamConsumer = ClientConfig::new()
         .set("bootstrap.servers", &config.kafka_broker)
-        .set("group.id", &config.consumer_group)
-        .set("enable.auto.commit", "true")
+        .set("group.id", group_id)
+        .set("enable.auto.commit", "false")
         .set("auto.offset.reset", "earliest")
         .create()?;
 
     Ok(consumer)
 }
+
+/// Create the relay that feeds delayed retries back into the input topic
+///
+/// It consumes with its own consumer group (`<consumer_group>_retry`).
+pub fn create_relay(config: &Config, delayed: DelayedRetryConfig) -> Result<Relay, IndexerError> {
+    Ok(Relay {
+        consumer: create_consumer(config, &format!("{}_retry", config.consumer_group))?,
+        producer: create_producer(config)?,
+        delayed,
+        input_topic: config.input_topic.clone(),
+        send_timeout: Duration::from_secs(config.kafka_send_timeout_secs),
+    })
+}
+
+/// Create a Kafka producer for routing failed messages to the dead-letter topic
+pub fn create_producer(config: &Config) -> Result<FutureProducer, IndexerError> {
+    let producer: FutureProducer = ClientConfig::new()
+        .set("bootstrap.servers", &config.kafka_broker)
+        .set("message.timeout.ms", &config.kafka_timeout_ms)
+        .create()?;
+
+    Ok(producer)
+}
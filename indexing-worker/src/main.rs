@@ -1,13 +1,21 @@
 mod config;
+mod dlq;
 mod error;
 mod handler;
 mod kafka;
 mod qdrant;
 
 use config::Config;
-use rdkafka::consumer::Consumer;
+use error::IndexerError;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
 use rdkafka::message::Message;
-use shared_types::DocumentVectorizedEvent;
+use rdkafka::producer::{FutureProducer, Producer};
+use rdkafka::util::Timeout;
+use shared_runtime::retry::{self, Failure, Retryable};
+use shared_runtime::{Shutdown, status, tombstone};
+use shared_types::{DocumentStage, DocumentStatusEvent, DocumentVectorizedEvent};
+use std::time::Duration;
+use uuid::Uuid;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -21,7 +29,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     });
 
     let qdrant_client = qdrant::create_client(&config).await?;
-    let consumer = kafka::create_consumer(&config)?;
+    let consumer = kafka::create_consumer(&config, &config.consumer_group)?;
+    let producer = kafka::create_producer(&config)?;
 
     consumer.subscribe(&[&config.input_topic])?;
     tracing::info!(
@@ -30,26 +39,229 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "Indexing Worker started. Listening for vectors..."
     );
 
+    let shutdown = Shutdown::listen();
+
+    // Delayed retries are fed back into the input topic by a separate relay
+    let relay = config
+        .retry
+        .delayed
+        .clone()
+        .map(|delayed| kafka::create_relay(&config, delayed))
+        .transpose()?
+        .map(|relay| {
+            let relay_shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                if let Err(e) = relay.run::<IndexerError>(relay_shutdown).await {
+                    tracing::error!(error = %e, "Delayed-retry relay stopped");
+                }
+            })
+        });
+
+    // Drain in-flight work after a signal, bounded by the shutdown deadline.
+    // An abandoned message is not committed and will be re-delivered.
+    let draining = async {
+        run(&consumer, &producer, &qdrant_client, &config, &shutdown).await?;
+        if let Some(relay) = relay {
+            let _ = relay.await;
+        }
+        Ok::<(), IndexerError>(())
+    };
+    tokio::select! {
+        result = draining => result?,
+        _ = shutdown.deadline(Duration::from_secs(config.shutdown_timeout_secs)) => {
+            tracing::warn!("Shutdown deadline exceeded, abandoning in-flight message");
+        }
+    }
+
+    producer.flush(Timeout::After(Duration::from_secs(
+        config.kafka_send_timeout_secs,
+    )))?;
+    tracing::info!("Indexing Worker stopped");
+
+    Ok(())
+}
+
+/// Main processing loop
+///
+/// Returns once a shutdown signal has been received and the current message
+/// has been handled and committed.
+async fn run(
+    consumer: &StreamConsumer,
+    producer: &FutureProducer,
+    qdrant_client: &qdrant_client::Qdrant,
+    config: &Config,
+    shutdown: &Shutdown,
+) -> Result<(), IndexerError> {
     loop {
-        match consumer.recv().await {
-            Err(e) => tracing::warn!(error = %e, "Kafka receive error"),
-            Ok(message) => {
-                if let Some(payload_bytes) = message.payload() {
-                    match serde_json::from_slice::<DocumentVectorizedEvent>(payload_bytes) {
-                        Ok(event) => {
-                            let doc_id = event.id;
-                            if let Err(e) =
-                                handler::process_vector_event(event, &qdrant_client, &config).await
-                            {
-                                tracing::error!(%doc_id, error = %e, "Failed to index document");
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!(error = %e, "Failed to deserialize message payload");
-                        }
-                    }
+        let received = tokio::select! {
+            biased;
+            _ = shutdown.triggered() => return Ok(()),
+            received = consumer.recv() => received,
+        };
+        let message = match received {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!(error = %e, "Kafka receive error");
+                continue;
+            }
+        };
+
+        handle_message(&message, producer, qdrant_client, config).await?;
+
+        // Indexed, dead-lettered or scheduled for retry: safe to move past it.
+        // The last commit before exiting is synchronous so it is not lost.
+        let mode = if shutdown.is_triggered() {
+            CommitMode::Sync
+        } else {
+            CommitMode::Async
+        };
+        consumer.commit_message(&message, mode)?;
+    }
+}
+
+/// Index a single message, routing it to the retry or dead-letter topic on failure
+async fn handle_message(
+    message: &impl Message,
+    producer: &FutureProducer,
+    qdrant_client: &qdrant_client::Qdrant,
+    config: &Config,
+) -> Result<(), IndexerError> {
+    let event = match parse_event(message) {
+        Ok(event) => event,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to deserialize message payload");
+            return handle_failure(
+                producer,
+                config,
+                message,
+                dlq::Stage::Deserialize,
+                Failure::once(e),
+            )
+            .await;
+        }
+    };
+
+    match event {
+        Incoming::Document(event) => {
+            let doc_id = event.id;
+            let result = retry::with_backoff(&config.retry, || {
+                handler::process_vector_event(&event, qdrant_client, config)
+            })
+            .await;
+            match result {
+                // An outdated version is reported too; the status store ignores it.
+                // A document split over several events is indexed once its last part is.
+                Ok(()) if !handler::completes_document(&event) => {}
+                Ok(()) => {
+                    let indexed = DocumentStatusEvent::now(
+                        doc_id,
+                        DocumentStage::Indexed,
+                        Some(event.version),
+                    );
+                    status::publish(producer, &config.status_topic, indexed);
+                }
+                Err(failure) => {
+                    tracing::error!(%doc_id, error = %failure.error, "Failed to index document");
+                    handle_failure(producer, config, message, dlq::Stage::Index, failure).await?;
+                }
+            }
+        }
+        Incoming::Deletion { doc_id, version } => {
+            let result = retry::with_backoff(&config.retry, || {
+                handler::process_deletion(doc_id, version, qdrant_client, config)
+            })
+            .await;
+            match result {
+                // A skipped deletion is reported too; the status store ignores it
+                Ok(()) => {
+                    let deleted = DocumentStatusEvent::now(doc_id, DocumentStage::Deleted, version);
+                    status::publish(producer, &config.status_topic, deleted);
+                }
+                Err(failure) => {
+                    tracing::error!(%doc_id, error = %failure.error, "Failed to delete document");
+                    handle_failure(producer, config, message, dlq::Stage::Delete, failure).await?;
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    Ok(())
+}
+
+/// Route a message whose processing failed
+///
+/// Transient failures go to the delayed-retry topic while it has attempts
+/// left; everything else is dead-lettered. Fails only if neither publish
+/// succeeds, so the worker stops instead of dropping the message.
+async fn handle_failure(
+    producer: &FutureProducer,
+    config: &Config,
+    message: &impl Message,
+    stage: dlq::Stage,
+    failure: Failure<IndexerError>,
+) -> Result<(), IndexerError> {
+    if failure.error.is_retryable()
+        && retry::schedule_delayed::<IndexerError>(
+            producer,
+            &config.retry,
+            Duration::from_secs(config.kafka_send_timeout_secs),
+            message,
+        )
+        .await?
+    {
+        return Ok(());
+    }
+
+    let attempts = retry::total_attempts(&config.retry, message, failure.attempts);
+    let reason = failure.error.to_string();
+    dlq::dead_letter::<IndexerError>(
+        producer,
+        &config.dead_letter_topic,
+        Duration::from_secs(config.kafka_send_timeout_secs),
+        message,
+        stage.as_str(),
+        &reason,
+        attempts,
+    )
+    .await?;
+    status::publish_failed(
+        producer,
+        &config.status_topic,
+        message,
+        format!("{}: {}", stage.as_str(), reason),
+    );
+
+    Ok(())
+}
+
+/// What a message on the input topic asks the worker to do
+enum Incoming {
+    Document(Box<DocumentVectorizedEvent>),
+    /// A tombstone: keyed by the document ID, without a payload, and
+    /// carrying the version it deletes up to in a header
+    Deletion {
+        doc_id: Uuid,
+        version: Option<u64>,
+    },
+}
+
+/// Deserialize the vectorized event or deletion carried by a message
+fn parse_event(message: &impl Message) -> Result<Incoming, IndexerError> {
+    let Some(payload_bytes) = message.payload() else {
+        let doc_id = message
+            .key_view::<str>()
+            .and_then(Result::ok)
+            .and_then(|key| Uuid::parse_str(key).ok())
+            .ok_or_else(|| {
+                IndexerError::SerializationError(
+                    "message has neither a payload nor a document ID key".to_string(),
+                )
+            })?;
+        return Ok(Incoming::Deletion {
+            doc_id,
+            version: tombstone::version(message),
+        });
+    };
+
+    Ok(Incoming::Document(serde_json::from_slice(payload_bytes)?))
+}
@@ -1,9 +1,20 @@
 use crate::config::Config;
 use crate::error::IndexerError;
+use qdrant_client::Payload;
+use qdrant_client::qdrant::point_id::PointIdOptions;
+use qdrant_client::qdrant::{
+    Condition, CreateFieldIndexCollectionBuilder, FieldType, Filter, PointsIdsList,
+    ScrollPointsBuilder, SetPayloadPointsBuilder,
+};
+
+/// Points migrated per scroll page
+const MIGRATION_PAGE_SIZE: u32 = 256;
 
 /// Initialize and return a Qdrant gRPC client
 ///
-/// Connects to the Qdrant instance and verifies the target collection exists.
+/// Connects to the Qdrant instance, verifies the target collection exists,
+/// makes sure the chunk payload fields are indexed and migrates points indexed
+/// before documents were chunked.
 pub async fn create_client(config: &Config) -> Result<qdrant_client::Qdrant, IndexerError> {
     let client = qdrant_client::Qdrant::from_url(&config.qdrant_grpc_url)
         .build()
@@ -15,6 +26,9 @@ pub async fn create_client(config: &Config) -> Result<qdrant_client::Qdrant, Ind
         .await
         .map_err(|e| IndexerError::QdrantConnectionError(e.to_string()))?;
 
+    ensure_payload_indexes(&client, &config.collection_name).await?;
+    migrate_unchunked_points(&client, &config.collection_name).await?;
+
     tracing::info!(
         collection = %config.collection_name,
         url = %config.qdrant_grpc_url,
@@ -23,3 +37,97 @@ pub async fn create_client(config: &Config) -> Result<qdrant_client::Qdrant, Ind
 
     Ok(client)
 }
+
+/// Index the fields used to group and clean up chunks of the same document
+///
+/// Creating an index that already exists is a no-op in Qdrant.
+async fn ensure_payload_indexes(
+    client: &qdrant_client::Qdrant,
+    collection_name: &str,
+) -> Result<(), IndexerError> {
+    for (field, field_type) in [
+        ("document_id", FieldType::Keyword),
+        ("chunk_index", FieldType::Integer),
+    ] {
+        client
+            .create_field_index(
+                CreateFieldIndexCollectionBuilder::new(collection_name, field, field_type)
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| IndexerError::QdrantConnectionError(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Give points indexed before chunking the payload of a single-chunk document
+///
+/// Such points use the document UUID as their ID and have no `document_id`,
+/// so grouped searches, which group by that field, would never return them.
+/// Deletion markers share that shape but carry `deleted_version` instead.
+/// Their content was not stored, so the chunk span is left empty.
+async fn migrate_unchunked_points(
+    client: &qdrant_client::Qdrant,
+    collection_name: &str,
+) -> Result<(), IndexerError> {
+    let mut offset = None;
+    let mut migrated = 0usize;
+
+    loop {
+        let mut request = ScrollPointsBuilder::new(collection_name)
+            .filter(Filter::must([
+                Condition::is_empty("document_id"),
+                Condition::is_empty("deleted_version"),
+            ]))
+            .limit(MIGRATION_PAGE_SIZE)
+            .with_payload(false);
+        if let Some(offset) = offset.take() {
+            request = request.offset(offset);
+        }
+        let response = client
+            .scroll(request)
+            .await
+            .map_err(|e| IndexerError::QdrantConnectionError(e.to_string()))?;
+
+        for point in response.result {
+            let Some(id) = point.id else { continue };
+            let Some(PointIdOptions::Uuid(doc_id)) = id.point_id_options.clone() else {
+                tracing::warn!(point_id = ?id, "Skipping unchunked point without a UUID id");
+                continue;
+            };
+
+            let mut payload = Payload::new();
+            payload.insert("document_id", doc_id);
+            payload.insert("chunk_index", 0i64);
+            payload.insert("chunk_count", 1i64);
+            payload.insert("chunk_start", 0i64);
+            payload.insert("chunk_end", 0i64);
+
+            client
+                .set_payload(
+                    SetPayloadPointsBuilder::new(collection_name, payload)
+                        .points_selector(PointsIdsList { ids: vec![id] })
+                        .wait(true),
+                )
+                .await
+                .map_err(|e| IndexerError::QdrantConnectionError(e.to_string()))?;
+            migrated += 1;
+        }
+
+        match response.next_page_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    if migrated > 0 {
+        tracing::info!(
+            collection = %collection_name,
+            migrated,
+            "Migrated points indexed before chunking"
+        );
+    }
+
+    Ok(())
+}
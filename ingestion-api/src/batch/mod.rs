@@ -0,0 +1,306 @@
+use crate::{
+    error::ApiError,
+    ids::{self, IdempotencyKey},
+    kafka,
+    state::AppState,
+    validation,
+};
+use axum::body::Body;
+use futures::stream::{FuturesOrdered, StreamExt, TryStreamExt};
+use shared_types::{BatchItemResult, BatchItemStatus, BatchSubmitResponse, DocumentSubmission};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+use tokio_util::io::StreamReader;
+use uuid::Uuid;
+
+/// Read a JSON array batch body, refusing one larger than `max_bytes`
+///
+/// Nothing has been published yet at this point, so an oversized body fails
+/// the whole request with 413.
+pub async fn read_json_array(body: Body, max_bytes: usize) -> Result<Vec<u8>, ApiError> {
+    let mut bytes = Vec::new();
+    StreamReader::new(body.into_data_stream().map_err(std::io::Error::other))
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|e| ApiError::InvalidBatch(format!("failed to read batch: {}", e)))?;
+    if bytes.len() > max_bytes {
+        return Err(ApiError::PayloadTooLarge(format!(
+            "batch exceeds the limit of {} bytes",
+            max_bytes
+        )));
+    }
+    Ok(bytes)
+}
+
+/// Publish every entry of a JSON array concurrently
+///
+/// Entries are parsed one by one so a malformed document only rejects
+/// itself instead of failing the whole batch.
+pub async fn submit_json_array(
+    state: Arc<AppState>,
+    body: &[u8],
+    idempotency_key: Option<&IdempotencyKey>,
+) -> Result<BatchSubmitResponse, ApiError> {
+    let entries: Vec<serde_json::Value> = serde_json::from_slice(body).map_err(|e| {
+        ApiError::InvalidBatch(format!("expected a JSON array of documents: {}", e))
+    })?;
+
+    let results = futures::stream::iter(entries.into_iter().enumerate())
+        .map(|(index, entry)| {
+            let entry_key = idempotency_key.map(|key| key.entry(index));
+            submit_entry(
+                state.clone(),
+                index,
+                serde_json::from_value(entry),
+                entry_key,
+            )
+        })
+        .buffered(state.config.batch_max_in_flight)
+        .collect()
+        .await;
+
+    Ok(summarize(results))
+}
+
+/// Publish documents from an NDJSON stream as lines arrive
+///
+/// Each line is handed to its own task so publishing overlaps with reading
+/// the request body; at most `batch_max_in_flight` publishes are pending.
+///
+/// No body limit applies to a raw stream, so reading stops once the body
+/// exceeds `batch_max_body_bytes`, a line exceeds the `/submit` body limit,
+/// or a line follows the last of `batch_max_documents` documents. Documents
+/// read before that point have already been handed off, so their publishes
+/// are awaited and reported, followed by a rejected entry for the line that
+/// overflowed; the rest of the body is ignored.
+pub async fn submit_ndjson(
+    state: Arc<AppState>,
+    body: Body,
+    idempotency_key: Option<&IdempotencyKey>,
+) -> BatchSubmitResponse {
+    let mut reader = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+    let mut in_flight = FuturesOrdered::new();
+    let mut results = Vec::new();
+    let mut line = Vec::new();
+    let mut index = 0;
+    let mut body_bytes = 0;
+    let mut overflow = None;
+    let max_line_bytes = state.config.validation.max_body_bytes;
+
+    loop {
+        line.clear();
+        // Never buffer more than one line's worth, plus a byte to detect overflow
+        let read = match (&mut reader)
+            .take(max_line_bytes as u64 + 1)
+            .read_until(b'\n', &mut line)
+            .await
+        {
+            Ok(read) => read,
+            Err(e) => {
+                overflow = Some(format!("failed to read NDJSON stream: {}", e));
+                break;
+            }
+        };
+        if read == 0 {
+            break;
+        }
+
+        body_bytes += read;
+        if body_bytes > state.config.batch_max_body_bytes {
+            overflow = Some(format!(
+                "batch exceeds the limit of {} bytes",
+                state.config.batch_max_body_bytes
+            ));
+            break;
+        }
+        if read > max_line_bytes && line.last() != Some(&b'\n') {
+            overflow = Some(format!(
+                "line {} exceeds the limit of {} bytes",
+                index + 1,
+                max_line_bytes
+            ));
+            break;
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        if index >= state.config.batch_max_documents {
+            overflow = Some(format!(
+                "batch exceeds the limit of {} documents",
+                state.config.batch_max_documents
+            ));
+            break;
+        }
+
+        let entry_key = idempotency_key.map(|key| key.entry(index));
+        let task = tokio::spawn(submit_entry(
+            state.clone(),
+            index,
+            serde_json::from_slice(&line),
+            entry_key,
+        ));
+        in_flight.push_back(async move {
+            task.await
+                .unwrap_or_else(|e| rejected(index, None, format!("publish task failed: {}", e)))
+        });
+        index += 1;
+
+        if in_flight.len() >= state.config.batch_max_in_flight
+            && let Some(result) = in_flight.next().await
+        {
+            results.push(result);
+        }
+    }
+
+    while let Some(result) = in_flight.next().await {
+        results.push(result);
+    }
+    if let Some(reason) = overflow {
+        results.push(rejected(index, None, reason));
+    }
+
+    summarize(results)
+}
+
+/// Publish a single parsed batch entry and record its outcome
+async fn submit_entry(
+    state: Arc<AppState>,
+    index: usize,
+    parsed: Result<DocumentSubmission, serde_json::Error>,
+    idempotency_key: Option<IdempotencyKey>,
+) -> BatchItemResult {
+    if index >= state.config.batch_max_documents {
+        return rejected(
+            index,
+            None,
+            format!(
+                "batch exceeds the limit of {} documents",
+                state.config.batch_max_documents
+            ),
+        );
+    }
+
+    let submission = match parsed {
+        Ok(submission) => submission,
+        Err(e) => return rejected(index, None, format!("invalid document: {}", e)),
+    };
+    if let Err(e) = validation::validate(&submission, &state.config.validation) {
+        return rejected(index, submission.id, e.to_string());
+    }
+
+    if let Some(key) = &idempotency_key
+        && let Err(e) = state.idempotency_keys.claim(key, &submission)
+    {
+        return rejected(index, submission.id, e.to_string());
+    }
+
+    let id = ids::document_id(submission.id, idempotency_key.as_ref());
+    let payload = submission.into_payload(id);
+    match kafka::publish_document_event(&state.producer, &state.config, payload).await {
+        Ok(()) => BatchItemResult {
+            index,
+            id: Some(id),
+            status: BatchItemStatus::Accepted,
+            reason: None,
+        },
+        Err(e) => rejected(index, Some(id), e.to_string()),
+    }
+}
+
+fn rejected(index: usize, id: Option<Uuid>, reason: String) -> BatchItemResult {
+    BatchItemResult {
+        index,
+        id,
+        status: BatchItemStatus::Rejected,
+        reason: Some(reason),
+    }
+}
+
+fn summarize(results: Vec<BatchItemResult>) -> BatchSubmitResponse {
+    let accepted = results
+        .iter()
+        .filter(|r| r.status == BatchItemStatus::Accepted)
+        .count();
+
+    BatchSubmitResponse {
+        accepted,
+        rejected: results.len() - accepted,
+        results,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, kafka::create_producer, status::StatusStore};
+
+    /// Every line used below fails to parse, so nothing reaches Kafka
+    fn state(configure: impl FnOnce(&mut Config)) -> Arc<AppState> {
+        let mut config = Config::default_config();
+        configure(&mut config);
+        let producer = create_producer(&config).unwrap();
+        let statuses = StatusStore::new(config.status_max_documents);
+        Arc::new(AppState::new(producer, config, statuses))
+    }
+
+    fn reasons(response: &BatchSubmitResponse) -> Vec<(usize, &str)> {
+        response
+            .results
+            .iter()
+            .map(|r| (r.index, r.reason.as_deref().unwrap_or_default()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn ndjson_past_the_document_limit_reports_what_was_read() {
+        let state = state(|config| config.batch_max_documents = 2);
+        let body = Body::from("not json\n\nnot json\nnot json\nnot json\n");
+
+        let response = submit_ndjson(state, body, None).await;
+
+        assert_eq!(response.accepted, 0);
+        assert_eq!(response.rejected, 3);
+        let reasons = reasons(&response);
+        assert!(reasons[0].1.starts_with("invalid document"));
+        assert!(reasons[1].1.starts_with("invalid document"));
+        assert_eq!(reasons[2], (2, "batch exceeds the limit of 2 documents"));
+    }
+
+    #[tokio::test]
+    async fn ndjson_past_the_body_limit_reports_what_was_read() {
+        let state = state(|config| config.batch_max_body_bytes = 20);
+        let body = Body::from("not json\nnot json\nnot json\n");
+
+        let response = submit_ndjson(state, body, None).await;
+
+        let reasons = reasons(&response);
+        assert_eq!(reasons.len(), 3);
+        assert_eq!(reasons[2], (2, "batch exceeds the limit of 20 bytes"));
+    }
+
+    #[tokio::test]
+    async fn ndjson_line_past_the_submit_limit_ends_the_batch() {
+        let state = state(|config| config.validation.max_body_bytes = 10);
+        let body = Body::from("not json\nthis line is far too long\nnot json\n");
+
+        let response = submit_ndjson(state, body, None).await;
+
+        let reasons = reasons(&response);
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(reasons[1], (1, "line 2 exceeds the limit of 10 bytes"));
+    }
+
+    #[tokio::test]
+    async fn oversized_json_array_is_too_large() {
+        let body = Body::from(format!("[{}]", "1,".repeat(20)));
+
+        let error = read_json_array(body, 16).await.unwrap_err();
+
+        assert!(matches!(error, ApiError::PayloadTooLarge(_)));
+        assert_eq!(
+            read_json_array(Body::from("[]"), 16).await.unwrap(),
+            b"[]".to_vec()
+        );
+    }
+}
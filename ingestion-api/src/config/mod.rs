@@ -5,11 +5,48 @@ use std::fs;
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub kafka_topic: String,
+    pub status_topic: String,
     pub kafka_broker: String,
     pub server_host: String,
     pub server_port: u16,
     pub kafka_timeout_ms: String,
     pub kafka_send_timeout_secs: u64,
+    pub batch_max_documents: usize,
+    pub batch_max_in_flight: usize,
+    pub batch_max_body_bytes: usize,
+    pub upload_max_bytes: usize,
+    /// Largest size the compressed parts of an uploaded PDF or DOCX file may expand to
+    pub upload_max_expanded_bytes: usize,
+    /// Documents whose status is kept in memory; the least recently updated are dropped
+    pub status_max_documents: usize,
+    /// Idempotency keys remembered to detect reuse; the least recently used are dropped
+    pub idempotency_max_keys: usize,
+    pub validation: ValidationConfig,
+    pub shutdown_timeout_secs: u64,
+}
+
+/// Rules a document must pass before it is published
+#[derive(Debug, Deserialize, Clone)]
+pub struct ValidationConfig {
+    /// Fewest characters of content, ignoring surrounding whitespace
+    pub min_content_chars: usize,
+    /// Largest content in bytes; keeps received events below the broker's message size limit
+    pub max_content_bytes: usize,
+    /// Largest share of control and replacement characters before content counts as binary
+    pub max_non_text_ratio: f64,
+    /// Largest `POST /submit` request body in bytes
+    pub max_body_bytes: usize,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            min_content_chars: 1,
+            max_content_bytes: 512 * 1024,
+            max_non_text_ratio: 0.01,
+            max_body_bytes: 1024 * 1024,
+        }
+    }
 }
 
 impl Config {
@@ -23,11 +60,21 @@ impl Config {
     pub fn default_config() -> Self {
         Self {
             kafka_topic: "resume_received".to_string(),
+            status_topic: "document_status".to_string(),
             kafka_broker: "127.0.0.1:9092".to_string(),
             server_host: "0.0.0.0".to_string(),
             server_port: 3000,
             kafka_timeout_ms: "5000".to_string(),
             kafka_send_timeout_secs: 2,
+            batch_max_documents: 1000,
+            batch_max_in_flight: 64,
+            batch_max_body_bytes: 64 * 1024 * 1024,
+            upload_max_bytes: 10 * 1024 * 1024,
+            upload_max_expanded_bytes: 64 * 1024 * 1024,
+            status_max_documents: 100_000,
+            idempotency_max_keys: 100_000,
+            validation: ValidationConfig::default(),
+            shutdown_timeout_secs: 30,
         }
     }
 }
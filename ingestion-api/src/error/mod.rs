@@ -5,11 +5,21 @@ use axum::{
 };
 use serde_json::json;
 
+use crate::validation::Violation;
+
 /// Custom error type for the ingestion API
 #[derive(Debug)]
 pub enum ApiError {
     SerializationError(String),
     KafkaPublishError(String),
+    InvalidBatch(String),
+    InvalidRequest(String),
+    NotFound(String),
+    UnsupportedMediaType(String),
+    UnprocessableDocument(String),
+    PayloadTooLarge(String),
+    InvalidDocument(Vec<Violation>),
+    IdempotencyKeyReused(String),
 }
 
 impl std::fmt::Display for ApiError {
@@ -17,6 +27,16 @@ impl std::fmt::Display for ApiError {
         match self {
             ApiError::SerializationError(e) => write!(f, "Serialization error: {}", e),
             ApiError::KafkaPublishError(e) => write!(f, "Kafka publish error: {}", e),
+            ApiError::InvalidBatch(e) => write!(f, "Invalid batch: {}", e),
+            ApiError::InvalidRequest(e) => write!(f, "Invalid request: {}", e),
+            ApiError::NotFound(e) => write!(f, "Not found: {}", e),
+            ApiError::UnsupportedMediaType(e) => write!(f, "Unsupported media type: {}", e),
+            ApiError::UnprocessableDocument(e) => write!(f, "Unprocessable document: {}", e),
+            ApiError::PayloadTooLarge(e) => write!(f, "Payload too large: {}", e),
+            ApiError::InvalidDocument(violations) => {
+                write!(f, "Validation failed: {}", summarize(violations))
+            }
+            ApiError::IdempotencyKeyReused(e) => write!(f, "Idempotency key reused: {}", e),
         }
     }
 }
@@ -28,6 +48,16 @@ impl IntoResponse for ApiError {
         let (status, error_message) = match self {
             ApiError::SerializationError(e) => (StatusCode::INTERNAL_SERVER_ERROR, e),
             ApiError::KafkaPublishError(e) => (StatusCode::BAD_GATEWAY, e),
+            ApiError::InvalidBatch(e) => (StatusCode::BAD_REQUEST, e),
+            ApiError::InvalidRequest(e) => (StatusCode::BAD_REQUEST, e),
+            ApiError::NotFound(e) => (StatusCode::NOT_FOUND, e),
+            ApiError::UnsupportedMediaType(e) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, e),
+            ApiError::UnprocessableDocument(e) => (StatusCode::UNPROCESSABLE_ENTITY, e),
+            ApiError::PayloadTooLarge(e) => (StatusCode::PAYLOAD_TOO_LARGE, e),
+            ApiError::InvalidDocument(violations) => {
+                return validation_response(violations);
+            }
+            ApiError::IdempotencyKeyReused(e) => (StatusCode::UNPROCESSABLE_ENTITY, e),
         };
 
         let body = Json(json!({
@@ -37,3 +67,28 @@ impl IntoResponse for ApiError {
         (status, body).into_response()
     }
 }
+
+/// Validation failures list every failed rule in a structured body
+///
+/// A request that broke a size limit is answered with 413, any other with 400.
+fn validation_response(violations: Vec<Violation>) -> Response {
+    let status = if violations.iter().any(|v| v.rule.is_size_limit()) {
+        StatusCode::PAYLOAD_TOO_LARGE
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    let body = Json(json!({
+        "error": summarize(&violations),
+        "violations": violations,
+    }));
+
+    (status, body).into_response()
+}
+
+fn summarize(violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(|v| format!("{}: {}", v.field, v.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
@@ -0,0 +1,126 @@
+use super::{ExtractError, decode_entities};
+use std::io::{Cursor, Read};
+
+/// Part of a DOCX package holding the main document body
+const DOCUMENT_PART: &str = "word/document.xml";
+
+/// Extract the text of the main body of a DOCX (Office Open XML) file
+///
+/// Headers, footers, comments and footnotes live in other parts of the
+/// package and are not extracted.
+///
+/// The body part is decompressed up to `max_bytes`, so a small archive
+/// cannot expand into an unbounded amount of memory; a larger part is
+/// rejected.
+pub fn extract_text(bytes: &[u8], max_bytes: usize) -> Result<String, ExtractError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| ExtractError::Unreadable(format!("not a valid DOCX file: {}", e)))?;
+
+    let mut xml = Vec::new();
+    archive
+        .by_name(DOCUMENT_PART)
+        .map_err(|e| {
+            ExtractError::Unreadable(format!("DOCX file has no {}: {}", DOCUMENT_PART, e))
+        })?
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut xml)
+        .map_err(|e| {
+            ExtractError::Unreadable(format!("failed to read {}: {}", DOCUMENT_PART, e))
+        })?;
+    if xml.len() > max_bytes {
+        return Err(ExtractError::TooLarge(format!(
+            "{} of the DOCX file exceeds the limit of {} bytes",
+            DOCUMENT_PART, max_bytes
+        )));
+    }
+
+    let xml = String::from_utf8(xml)
+        .map_err(|_| ExtractError::Unreadable(format!("{} is not valid UTF-8", DOCUMENT_PART)))?;
+    Ok(body_text(&xml))
+}
+
+/// Text runs of WordprocessingML, one paragraph per line
+///
+/// Only the content of `<w:t>` elements is text; everything else is
+/// markup. Paragraph, tab and break elements are kept as whitespace.
+fn body_text(xml: &str) -> String {
+    let mut text = String::with_capacity(xml.len() / 4);
+    let mut in_text_run = false;
+    let mut rest = xml;
+
+    while let Some(open) = rest.find('<') {
+        if in_text_run {
+            text.push_str(&decode_entities(&rest[..open]));
+        }
+
+        let Some(close) = rest[open..].find('>') else {
+            break;
+        };
+        let tag = &rest[open + 1..open + close];
+        rest = &rest[open + close + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+
+        match (name, closing) {
+            ("w:t", false) => in_text_run = !tag.ends_with('/'),
+            ("w:t", true) => in_text_run = false,
+            ("w:p", true) => text.push_str("\n\n"),
+            ("w:tab", false) => text.push('\t'),
+            ("w:br" | "w:cr", false) => text.push('\n'),
+            _ => {}
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::{FileOptions, ZipWriter};
+
+    fn docx(document: &str) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file(DOCUMENT_PART, FileOptions::default())
+            .unwrap();
+        writer.write_all(document.as_bytes()).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn body_text_keeps_paragraphs_tabs_and_entities() {
+        let xml = r#"<w:document><w:body><w:p><w:r><w:t>Rust &amp; Go</w:t><w:tab/><w:t xml:space="preserve">5 years</w:t></w:r></w:p><w:p><w:r><w:t>Kafka</w:t><w:br/><w:t/></w:r></w:p></w:body></w:document>"#;
+
+        assert_eq!(
+            extract_text(&docx(xml), 1024).unwrap(),
+            "Rust & Go\t5 years\n\nKafka\n\n\n"
+        );
+    }
+
+    #[test]
+    fn body_part_larger_than_the_limit_is_rejected() {
+        let xml = format!("<w:p><w:t>{}</w:t></w:p>", "a".repeat(1000));
+        let bytes = docx(&xml);
+
+        assert!(extract_text(&bytes, xml.len()).is_ok());
+        assert!(matches!(
+            extract_text(&bytes, xml.len() - 1),
+            Err(ExtractError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn files_that_are_not_docx_are_unreadable() {
+        assert!(matches!(
+            extract_text(b"not a zip", 1024),
+            Err(ExtractError::Unreadable(_))
+        ));
+    }
+}
@@ -0,0 +1,196 @@
+use super::decode_entities;
+
+/// Elements whose content is never visible text
+const HIDDEN_ELEMENTS: [&str; 4] = ["script", "style", "noscript", "template"];
+
+/// Elements that start a new line of text
+const BLOCK_ELEMENTS: [&str; 26] = [
+    "p",
+    "div",
+    "br",
+    "hr",
+    "li",
+    "ul",
+    "ol",
+    "dl",
+    "dt",
+    "dd",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "tr",
+    "table",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "blockquote",
+    "pre",
+    "title",
+    "main",
+];
+
+/// Extract the visible text of an HTML document
+///
+/// A tolerant tag stripper rather than a full HTML parser: tags are
+/// dropped, block elements become paragraph breaks and character
+/// references are decoded. Scripts and styles are skipped.
+pub fn extract_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut hidden: Option<String> = None;
+    let mut rest = html;
+    // Same byte offsets as `html`, for finding end tags regardless of case
+    let lowercase = html.to_ascii_lowercase();
+
+    loop {
+        // Script and style content may contain `<`; only its end tag closes it
+        if let Some(name) = hidden.take() {
+            let offset = html.len() - rest.len();
+            let Some(end) = lowercase[offset..].find(&format!("</{}", name)) else {
+                rest = "";
+                break;
+            };
+            rest = &rest[end..];
+        }
+
+        let Some(open) = rest.find('<') else {
+            break;
+        };
+        push_text(&mut text, &rest[..open]);
+        rest = &rest[open..];
+
+        // Comments and doctype declarations
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment
+                .find("-->")
+                .map_or("", |end| &comment[end + "-->".len()..]);
+            continue;
+        }
+
+        // An unterminated tag at the end is markup, not text
+        let Some(close) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..close];
+        rest = &rest[close + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        if !closing && !tag.ends_with('/') && HIDDEN_ELEMENTS.contains(&name.as_str()) {
+            hidden = Some(name);
+        } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            text.push_str("\n\n");
+        }
+    }
+    push_text(&mut text, rest);
+
+    text
+}
+
+/// Append a text node, collapsing whitespace the way a browser renders it
+fn push_text(text: &mut String, node: &str) {
+    if node.is_empty() {
+        return;
+    }
+
+    let decoded = decode_entities(node);
+    let mut words = decoded.split_whitespace().peekable();
+    if words.peek().is_none() {
+        if !decoded.is_empty() && !text.ends_with(char::is_whitespace) {
+            text.push(' ');
+        }
+        return;
+    }
+
+    if decoded.starts_with(char::is_whitespace) && !text.ends_with(char::is_whitespace) {
+        text.push(' ');
+    }
+    for (i, word) in words.enumerate() {
+        if i > 0 {
+            text.push(' ');
+        }
+        text.push_str(word);
+    }
+    if decoded.ends_with(char::is_whitespace) {
+        text.push(' ');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::tidy;
+
+    fn text(html: &str) -> String {
+        tidy(&extract_text(html))
+    }
+
+    #[test]
+    fn named_and_numeric_entities_are_decoded() {
+        assert_eq!(
+            text("<p>R&amp;D &lt;team&gt; &#65;&#x42;&#X43; caf&eacute;&nbsp;&copy;</p>"),
+            "R&D <team> ABC café ©"
+        );
+        assert_eq!(
+            text("Z&uuml;rich &Aring;se &szlig; 5&times;3 &laquo;x&raquo; &trade; co&shy;op"),
+            "Zürich Åse ß 5×3 «x» ™ coop"
+        );
+    }
+
+    #[test]
+    fn many_hidden_elements_are_skipped_in_linear_time() {
+        let html = "<script>x</script>a".repeat(20_000) + &"b".repeat(1_000_000);
+
+        let start = std::time::Instant::now();
+        let text = text(&html);
+
+        assert_eq!(text.len(), 1_020_000);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn malformed_entities_are_kept_literally() {
+        assert_eq!(
+            text("AT&T &#xZZ; &#1114112; a & b &amp &unknown; &EACUTE;"),
+            "AT&T &#xZZ; &#1114112; a & b &amp &unknown; &EACUTE;"
+        );
+    }
+
+    #[test]
+    fn block_elements_break_paragraphs_and_inline_elements_do_not() {
+        assert_eq!(
+            text(
+                "<h1>Jane Doe</h1><p>Senior <b>Rust</b>\n   engineer</p><ul><li>Kafka</li><li>Qdrant</li></ul>"
+            ),
+            "Jane Doe\n\nSenior Rust engineer\n\nKafka\n\nQdrant"
+        );
+    }
+
+    #[test]
+    fn scripts_styles_and_comments_are_skipped() {
+        assert_eq!(
+            text(
+                "<style>p { color: red }</style><SCRIPT>if (a < b) {}</SCRIPT><!-- <p>hidden</p> --><p>Visible</p>"
+            ),
+            "Visible"
+        );
+    }
+
+    #[test]
+    fn malformed_markup_does_not_panic() {
+        assert_eq!(text("<p>Unclosed <b"), "Unclosed");
+        assert_eq!(text("Before<!-- never closed <p>after</p>"), "Before");
+        assert_eq!(text("<script>never closed"), "");
+        assert_eq!(text("é<br/>ü"), "é\n\nü");
+    }
+}
@@ -0,0 +1,266 @@
+mod docx;
+mod html;
+mod pdf;
+mod rtf;
+
+use std::path::Path;
+
+/// File formats plain text can be extracted from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Pdf,
+    Docx,
+    Rtf,
+    Html,
+    PlainText,
+}
+
+impl Format {
+    /// Detect the format from the declared MIME type, or else the file extension
+    ///
+    /// Browsers often send `application/octet-stream` for files they do not
+    /// recognise, so a generic MIME type falls through to the extension.
+    pub fn detect(content_type: Option<&str>, file_name: Option<&str>) -> Option<Self> {
+        let mime = content_type
+            .map(|content_type| {
+                content_type
+                    .split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_ascii_lowercase()
+            })
+            .filter(|mime| !mime.is_empty() && mime != "application/octet-stream");
+
+        if let Some(mime) = mime {
+            return match mime.as_str() {
+                "application/pdf" => Some(Format::Pdf),
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+                    Some(Format::Docx)
+                }
+                "application/rtf" | "text/rtf" => Some(Format::Rtf),
+                "text/html" | "application/xhtml+xml" => Some(Format::Html),
+                "text/plain" => Some(Format::PlainText),
+                _ => None,
+            };
+        }
+
+        let extension = Path::new(file_name?)
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "pdf" => Some(Format::Pdf),
+            "docx" => Some(Format::Docx),
+            "rtf" => Some(Format::Rtf),
+            "html" | "htm" | "xhtml" => Some(Format::Html),
+            "txt" => Some(Format::PlainText),
+            _ => None,
+        }
+    }
+
+    /// Canonical MIME type, recorded in the document metadata
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Format::Pdf => "application/pdf",
+            Format::Docx => {
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+            }
+            Format::Rtf => "application/rtf",
+            Format::Html => "text/html",
+            Format::PlainText => "text/plain",
+        }
+    }
+}
+
+/// Why no text could be extracted from a file
+#[derive(Debug)]
+pub enum ExtractError {
+    /// The file is malformed or contains no text
+    Unreadable(String),
+    /// The file expands to more than the content size limit
+    TooLarge(String),
+}
+
+/// Size limits applied while extracting text
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Largest size the compressed parts of a file may expand to, markup included
+    pub max_expanded_bytes: usize,
+    /// Largest extracted text
+    pub max_content_bytes: usize,
+}
+
+/// Extract the plain text of a file
+///
+/// CPU-bound: call from a blocking task. Fails if the file is malformed or
+/// contains no text at all (e.g. a scanned PDF without a text layer).
+/// Compressed formats are never expanded beyond `max_expanded_bytes`, and
+/// text longer than `max_content_bytes` is rejected.
+pub fn extract_text(format: Format, bytes: &[u8], limits: Limits) -> Result<String, ExtractError> {
+    let text = match format {
+        Format::Pdf => pdf::extract_text(bytes, limits.max_expanded_bytes)?,
+        Format::Docx => docx::extract_text(bytes, limits.max_expanded_bytes)?,
+        Format::Rtf => rtf::extract_text(bytes).map_err(ExtractError::Unreadable)?,
+        Format::Html => html::extract_text(&String::from_utf8_lossy(bytes)),
+        Format::PlainText => String::from_utf8(bytes.to_vec())
+            .map_err(|_| ExtractError::Unreadable("text file is not valid UTF-8".to_string()))?,
+    };
+
+    let text = tidy(&text);
+    if text.is_empty() {
+        return Err(ExtractError::Unreadable(
+            "no text could be extracted from the file".to_string(),
+        ));
+    }
+    if text.len() > limits.max_content_bytes {
+        return Err(ExtractError::TooLarge(format!(
+            "the extracted text exceeds the limit of {} bytes",
+            limits.max_content_bytes
+        )));
+    }
+
+    Ok(text)
+}
+
+/// Trim every line and collapse runs of blank lines into one
+///
+/// Paragraph breaks survive as a single blank line, which the vector
+/// worker's paragraph chunking relies on.
+fn tidy(text: &str) -> String {
+    let mut tidied = String::with_capacity(text.len());
+    let mut blank_lines = 0;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !tidied.is_empty() {
+            tidied.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        tidied.push_str(line);
+        blank_lines = 0;
+    }
+
+    tidied
+}
+
+/// HTML entity names of the Latin-1 characters U+00A0 to U+00FF, in order
+const LATIN1_ENTITIES: [&str; 96] = [
+    "nbsp", "iexcl", "cent", "pound", "curren", "yen", "brvbar", "sect", "uml", "copy", "ordf",
+    "laquo", "not", "shy", "reg", "macr", "deg", "plusmn", "sup2", "sup3", "acute", "micro",
+    "para", "middot", "cedil", "sup1", "ordm", "raquo", "frac14", "frac12", "frac34", "iquest",
+    "Agrave", "Aacute", "Acirc", "Atilde", "Auml", "Aring", "AElig", "Ccedil", "Egrave", "Eacute",
+    "Ecirc", "Euml", "Igrave", "Iacute", "Icirc", "Iuml", "ETH", "Ntilde", "Ograve", "Oacute",
+    "Ocirc", "Otilde", "Ouml", "times", "Oslash", "Ugrave", "Uacute", "Ucirc", "Uuml", "Yacute",
+    "THORN", "szlig", "agrave", "aacute", "acirc", "atilde", "auml", "aring", "aelig", "ccedil",
+    "egrave", "eacute", "ecirc", "euml", "igrave", "iacute", "icirc", "iuml", "eth", "ntilde",
+    "ograve", "oacute", "ocirc", "otilde", "ouml", "divide", "oslash", "ugrave", "uacute", "ucirc",
+    "uuml", "yacute", "thorn", "yuml",
+];
+
+/// Decode the XML/HTML character reference starting after `&`
+///
+/// Returns the decoded text and the number of bytes consumed after `&`, or
+/// `None` if `rest` does not start with a reference this decoder knows.
+fn decode_entity(rest: &str) -> Option<(String, usize)> {
+    let end = rest.find(';').filter(|end| *end <= 32)?;
+    let name = &rest[..end];
+
+    let decoded = if let Some(code) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+        char::from_u32(u32::from_str_radix(code, 16).ok()?)?.to_string()
+    } else if let Some(code) = name.strip_prefix('#') {
+        char::from_u32(code.parse().ok()?)?.to_string()
+    } else {
+        match name {
+            "amp" => "&",
+            "lt" => "<",
+            "gt" => ">",
+            "quot" => "\"",
+            "apos" => "'",
+            "nbsp" => " ",
+            "shy" => "",
+            "ensp" | "emsp" | "thinsp" => " ",
+            "ndash" => "–",
+            "mdash" => "—",
+            "minus" => "−",
+            "hellip" => "…",
+            "bull" => "•",
+            "lsquo" => "‘",
+            "rsquo" => "’",
+            "sbquo" => "‚",
+            "ldquo" => "“",
+            "rdquo" => "”",
+            "bdquo" => "„",
+            "lsaquo" => "‹",
+            "rsaquo" => "›",
+            "dagger" => "†",
+            "Dagger" => "‡",
+            "permil" => "‰",
+            "trade" => "™",
+            "euro" => "€",
+            "OElig" => "Œ",
+            "oelig" => "œ",
+            "Scaron" => "Š",
+            "scaron" => "š",
+            "Yuml" => "Ÿ",
+            "larr" => "←",
+            "rarr" => "→",
+            _ => {
+                let offset = LATIN1_ENTITIES.iter().position(|entity| *entity == name)?;
+                return Some((char::from_u32(0xa0 + offset as u32)?.to_string(), end + 1));
+            }
+        }
+        .to_string()
+    };
+
+    Some((decoded, end + 1))
+}
+
+/// Replace character references in markup text
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp + 1..];
+        match decode_entity(rest) {
+            Some((entity, consumed)) => {
+                decoded.push_str(&entity);
+                rest = &rest[consumed..];
+            }
+            None => decoded.push('&'),
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        max_expanded_bytes: 1024,
+        max_content_bytes: 16,
+    };
+
+    #[test]
+    fn extracted_text_is_checked_against_the_content_limit() {
+        assert_eq!(
+            extract_text(Format::Html, b"<p>Short</p>", LIMITS).unwrap(),
+            "Short"
+        );
+        assert!(matches!(
+            extract_text(
+                Format::Html,
+                b"<p>Far longer than sixteen bytes</p>",
+                LIMITS
+            ),
+            Err(ExtractError::TooLarge(_))
+        ));
+    }
+}
@@ -0,0 +1,361 @@
+use super::ExtractError;
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+use weezl::{BitOrder, LzwStatus, decode::Decoder};
+
+/// A decoding step of a stream's `/Filter` chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    Flate,
+    Lzw,
+    Ascii85,
+    AsciiHex,
+    /// Image codecs and anything else the text extractor never expands
+    Other,
+}
+
+impl Filter {
+    fn from_name(name: &[u8]) -> Self {
+        match name {
+            b"FlateDecode" | b"Fl" => Filter::Flate,
+            b"LZWDecode" | b"LZW" => Filter::Lzw,
+            b"ASCII85Decode" | b"A85" => Filter::Ascii85,
+            b"ASCIIHexDecode" | b"AHx" => Filter::AsciiHex,
+            _ => Filter::Other,
+        }
+    }
+}
+
+/// Extract the text of a PDF file
+///
+/// `pdf-extract` decodes every stream of the file in full, so a small file
+/// could expand into an unbounded amount of memory. The streams are
+/// therefore decoded once up front, counting their size, and the file is
+/// rejected if they expand to more than `max_bytes` in total. Encrypted
+/// streams cannot be measured, so encrypted files are refused.
+pub fn extract_text(bytes: &[u8], max_bytes: usize) -> Result<String, ExtractError> {
+    if find(bytes, b"/Encrypt").is_some() {
+        return Err(ExtractError::Unreadable(
+            "encrypted PDF files are not supported".to_string(),
+        ));
+    }
+    if expanded_size(bytes, max_bytes) > max_bytes {
+        return Err(ExtractError::TooLarge(format!(
+            "the streams of the PDF file expand to more than {} bytes",
+            max_bytes
+        )));
+    }
+
+    pdf_extract::extract_text_from_mem(bytes).map_err(|e| ExtractError::Unreadable(e.to_string()))
+}
+
+/// Total decoded size of the filtered streams of a PDF, counted up to just past `limit`
+///
+/// Streams are found by their keywords rather than parsed, so object streams
+/// and the streams of damaged files the parser may still recover are
+/// covered too. A stream's filters are the last `/Filter` before it within
+/// its object; unfiltered streams are no larger than the file and are not
+/// counted.
+fn expanded_size(bytes: &[u8], limit: usize) -> usize {
+    let mut total = 0;
+    let mut at = 0;
+
+    while total <= limit {
+        let Some(stream) = find(&bytes[at..], b"stream").map(|i| at + i) else {
+            break;
+        };
+        let after_keyword = stream + b"stream".len();
+        let data_start = if bytes[after_keyword..].starts_with(b"\r\n") {
+            after_keyword + 2
+        } else if bytes[after_keyword..].starts_with(b"\n") {
+            after_keyword + 1
+        } else {
+            // Not a stream keyword, e.g. the tail of `endstream`
+            at = after_keyword;
+            continue;
+        };
+
+        // An object without a stream must not pass its filters on
+        let head = &bytes[at..stream];
+        let object_start = rfind(head, b"endobj").map_or(0, |end| end + b"endobj".len());
+        let filters = rfind(&head[object_start..], b"/Filter").map_or_else(Vec::new, |filter| {
+            parse_filters(&head[object_start + filter + b"/Filter".len()..])
+        });
+
+        let data_end =
+            find(&bytes[data_start..], b"endstream").map_or(bytes.len(), |end| data_start + end);
+        if !filters.is_empty() {
+            total += decoded_size(&bytes[data_start..data_end], &filters, limit - total + 1);
+        }
+        at = (data_end + b"endstream".len()).min(bytes.len());
+    }
+
+    total
+}
+
+/// The filter names following a `/Filter` key: a single name or an array of names
+fn parse_filters(value: &[u8]) -> Vec<Filter> {
+    let value = value.trim_ascii_start();
+    let names = match value.strip_prefix(b"[") {
+        Some(array) => &array[..array.iter().position(|b| *b == b']').unwrap_or(array.len())],
+        None if value.starts_with(b"/") => {
+            let end = value[1..]
+                .iter()
+                .position(|b| is_delimiter(*b))
+                .map_or(value.len(), |end| end + 1);
+            &value[..end]
+        }
+        None => return Vec::new(),
+    };
+
+    names
+        .split(|b| *b == b'/')
+        .map(|name| {
+            &name[..name
+                .iter()
+                .position(|b| is_delimiter(*b))
+                .unwrap_or(name.len())]
+        })
+        .filter(|name| !name.is_empty())
+        .map(Filter::from_name)
+        .collect()
+}
+
+/// Size of a stream after its filter chain, decoding at most `budget` bytes per step
+///
+/// Decoding stops at the first filter the text extractor does not expand.
+/// Data that fails to decode counts with what was decoded before the error.
+fn decoded_size(data: &[u8], filters: &[Filter], budget: usize) -> usize {
+    let mut decoded = data.to_vec();
+    let mut expanded = false;
+
+    for filter in filters {
+        decoded = match filter {
+            Filter::Flate => {
+                let mut output = Vec::new();
+                // A read error leaves what was decoded so far in the buffer
+                let _ = ZlibDecoder::new(decoded.as_slice())
+                    .take(budget as u64)
+                    .read_to_end(&mut output);
+                output
+            }
+            Filter::Lzw => lzw(&decoded, budget),
+            Filter::Ascii85 => ascii85(&decoded, budget),
+            Filter::AsciiHex => ascii_hex(&decoded),
+            Filter::Other => break,
+        };
+        expanded = true;
+    }
+
+    if expanded { decoded.len() } else { 0 }
+}
+
+/// Decode LZW data the way PDF writes it (early code size change)
+fn lzw(data: &[u8], budget: usize) -> Vec<u8> {
+    let mut decoder = Decoder::with_tiff_size_switch(BitOrder::Msb, 8);
+    let mut output = Vec::new();
+    let mut buffer = [0; 8192];
+    let mut input = data;
+
+    while output.len() < budget {
+        let result = decoder.decode_bytes(input, &mut buffer);
+        output.extend_from_slice(&buffer[..result.consumed_out]);
+        input = &input[result.consumed_in..];
+        if !matches!(result.status, Ok(LzwStatus::Ok)) {
+            break;
+        }
+    }
+
+    output
+}
+
+/// Decode ASCII base-85 data, up to its `~>` end marker
+fn ascii85(data: &[u8], budget: usize) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut group = [0u8; 5];
+    let mut len = 0;
+
+    for &byte in data {
+        if output.len() >= budget {
+            break;
+        }
+        match byte {
+            b'~' => break,
+            b'z' if len == 0 => output.extend_from_slice(&[0; 4]),
+            b'!'..=b'u' => {
+                group[len] = byte - b'!';
+                len += 1;
+                if len == 5 {
+                    output.extend_from_slice(&base85_word(&group).to_be_bytes());
+                    len = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+    // A final partial group is padded with the highest digit and truncated
+    if len > 1 {
+        group[len..].fill(84);
+        output.extend_from_slice(&base85_word(&group).to_be_bytes()[..len - 1]);
+    }
+
+    output
+}
+
+fn base85_word(group: &[u8; 5]) -> u32 {
+    group.iter().fold(0u32, |word, digit| {
+        word.wrapping_mul(85).wrapping_add(u32::from(*digit))
+    })
+}
+
+/// Decode hexadecimal data, up to its `>` end marker
+fn ascii_hex(data: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = data
+        .iter()
+        .take_while(|b| **b != b'>')
+        .filter_map(|b| (*b as char).to_digit(16))
+        .map(|digit| digit as u8)
+        .collect();
+
+    digits
+        .chunks(2)
+        .map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+/// Whether a byte ends a PDF name
+fn is_delimiter(byte: u8) -> bool {
+    byte.is_ascii_whitespace() || b"/[]<>()%{}".contains(&byte)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Compression, write::ZlibEncoder};
+    use std::io::Write;
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn pdf(objects: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.7\n".to_vec();
+        for (i, (dict, data)) in objects.iter().enumerate() {
+            pdf.extend_from_slice(format!("{} 0 obj\n{}\nstream\n", i + 1, dict).as_bytes());
+            pdf.extend_from_slice(data);
+            pdf.extend_from_slice(b"\nendstream\nendobj\n");
+        }
+        pdf.extend_from_slice(b"trailer\n<< /Root 1 0 R >>\n%%EOF\n");
+        pdf
+    }
+
+    #[test]
+    fn flate_streams_count_their_decoded_size() {
+        let content = vec![b'x'; 10_000];
+        let file = pdf(&[
+            ("<< /Length 1 /Filter /FlateDecode >>", deflate(&content)),
+            ("<< /Filter [/FlateDecode] /Length 1 >>", deflate(&content)),
+        ]);
+
+        assert_eq!(expanded_size(&file, 1_000_000), 20_000);
+    }
+
+    #[test]
+    fn counting_stops_past_the_limit() {
+        let bomb = deflate(&vec![0; 10_000_000]);
+        let file = pdf(&[("<< /Filter /FlateDecode >>", bomb)]);
+
+        assert!(file.len() < 20_000);
+        assert_eq!(expanded_size(&file, 1_000), 1_001);
+        assert!(matches!(
+            extract_text(&file, 1_000),
+            Err(ExtractError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn filter_chains_are_decoded_in_order() {
+        let inner = deflate(&vec![b'a'; 5_000]);
+        let twice = deflate(&inner);
+        let hex: String = deflate(&vec![b'b'; 3_000])
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let file = pdf(&[
+            ("<< /Filter [/FlateDecode /FlateDecode] >>", twice),
+            ("<< /Filter [/AHx /Fl] >>", format!("{}>", hex).into_bytes()),
+        ]);
+
+        assert_eq!(expanded_size(&file, 1_000_000), 8_000);
+    }
+
+    #[test]
+    fn ascii85_and_lzw_are_decoded() {
+        assert_eq!(ascii85(b"87cURD]i,\"Ebo80~>", 100), b"Hello World!");
+        assert_eq!(ascii85(b"z!!~>", 100), vec![0; 5]);
+
+        let mut encoder = weezl::encode::Encoder::with_tiff_size_switch(BitOrder::Msb, 8);
+        let encoded = encoder.encode(&vec![b'c'; 4_000]).unwrap();
+        assert_eq!(lzw(&encoded, 1_000_000), vec![b'c'; 4_000]);
+    }
+
+    #[test]
+    fn unfiltered_and_image_streams_are_not_counted() {
+        let file = pdf(&[
+            ("<< /Length 5 >>", b"BT ET".to_vec()),
+            ("<< /Filter /DCTDecode >>", vec![0xff; 1_000]),
+        ]);
+        assert_eq!(expanded_size(&file, 1_000_000), 0);
+
+        // Decoding stops at the image codec, after the Flate step has expanded
+        let file = pdf(&[(
+            "<< /Filter [/FlateDecode /DCTDecode] >>",
+            deflate(&[0xff; 1_000]),
+        )]);
+        assert_eq!(expanded_size(&file, 1_000_000), 1_000);
+    }
+
+    #[test]
+    fn filters_do_not_leak_into_the_next_object() {
+        let mut file = b"1 0 obj\n<< /Filter /Standard >>\nendobj\n".to_vec();
+        file.extend_from_slice(&pdf(&[("<< /Length 1 >>", deflate(&[b'x'; 1_000]))]));
+
+        assert_eq!(expanded_size(&file, 1_000_000), 0);
+    }
+
+    #[test]
+    fn encrypted_files_are_refused() {
+        let file = b"%PDF-1.7\ntrailer\n<< /Encrypt 5 0 R >>\n%%EOF\n";
+
+        assert!(matches!(
+            extract_text(file, 1_000_000),
+            Err(ExtractError::Unreadable(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_input_does_not_panic() {
+        assert_eq!(expanded_size(b"", 10), 0);
+        assert_eq!(expanded_size(b"stream", 10), 0);
+        assert_eq!(
+            expanded_size(b"/Filter /FlateDecode stream\n\x78\x9c", 10),
+            0
+        );
+        assert_eq!(expanded_size(b"/Filter [/FlateDecode", 10), 0);
+        assert_eq!(ascii_hex(b"4"), vec![0x40]);
+    }
+}
@@ -0,0 +1,269 @@
+/// Destinations whose content is document metadata, not body text
+const SKIPPED_DESTINATIONS: [&str; 14] = [
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "header",
+    "footer",
+    "headerl",
+    "headerr",
+    "footerl",
+    "footerr",
+    "listtable",
+    "listoverridetable",
+];
+
+/// State of the RTF group being read
+#[derive(Clone, Copy)]
+struct Group {
+    /// Inside a destination whose text is ignored
+    skipped: bool,
+    /// Number of fallback characters to drop after a `\u` escape
+    unicode_skip: usize,
+}
+
+/// Extract the plain text of an RTF document
+///
+/// Handles groups, paragraph and tab control words, hex-escaped code page
+/// characters (assumed Windows-1252) and `\u` Unicode escapes. Ignored
+/// destinations such as the font and colour tables are skipped, as is the
+/// binary data of `\binN`.
+pub fn extract_text(bytes: &[u8]) -> Result<String, String> {
+    if !bytes.starts_with(b"{\\rtf") {
+        return Err("not a valid RTF file".to_string());
+    }
+
+    let mut text = String::with_capacity(bytes.len() / 2);
+    let mut stack: Vec<Group> = Vec::new();
+    let mut group = Group {
+        skipped: false,
+        unicode_skip: 1,
+    };
+    // Fallback characters still to drop after the last `\u` escape
+    let mut pending_skip = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => {
+                stack.push(group);
+                i += 1;
+            }
+            b'}' => {
+                group = stack.pop().unwrap_or(group);
+                pending_skip = 0;
+                i += 1;
+            }
+            b'\\' => {
+                let (control, next) = control_word(bytes, i + 1);
+                i = next;
+
+                // A fallback character may itself be an escape
+                if pending_skip > 0 && !matches!(control, Control::Word(..)) {
+                    pending_skip -= 1;
+                    continue;
+                }
+
+                match control {
+                    // N raw bytes follow, which may contain braces and backslashes
+                    Control::Word("bin", Some(n)) => {
+                        i = i.saturating_add(n.max(0) as usize).min(bytes.len())
+                    }
+                    Control::Word("*", _) => group.skipped = true,
+                    Control::Word(word, _) if SKIPPED_DESTINATIONS.contains(&word) => {
+                        group.skipped = true
+                    }
+                    Control::Word("uc", Some(n)) => group.unicode_skip = n.max(0) as usize,
+                    Control::Word("u", Some(code)) => {
+                        if !group.skipped {
+                            // Code points above 32767 are written as negative numbers
+                            let code = if code < 0 { code + 65536 } else { code };
+                            text.push(
+                                char::from_u32(code as u32).unwrap_or(char::REPLACEMENT_CHARACTER),
+                            );
+                        }
+                        pending_skip = group.unicode_skip;
+                    }
+                    Control::Word("par" | "line" | "sect" | "page", _) if !group.skipped => {
+                        text.push('\n')
+                    }
+                    Control::Word("tab", _) if !group.skipped => text.push('\t'),
+                    Control::Word("emdash", _) if !group.skipped => text.push('—'),
+                    Control::Word("endash", _) if !group.skipped => text.push('–'),
+                    Control::Word("bullet", _) if !group.skipped => text.push('•'),
+                    Control::Word("lquote", _) if !group.skipped => text.push('‘'),
+                    Control::Word("rquote", _) if !group.skipped => text.push('’'),
+                    Control::Word("ldblquote", _) if !group.skipped => text.push('“'),
+                    Control::Word("rdblquote", _) if !group.skipped => text.push('”'),
+                    Control::Hex(byte) if !group.skipped => text.push(windows_1252(byte)),
+                    Control::Symbol(symbol) if !group.skipped => match symbol {
+                        b'\\' | b'{' | b'}' => text.push(symbol as char),
+                        b'~' => text.push('\u{a0}'),
+                        b'_' => text.push('-'),
+                        b'\n' | b'\r' => text.push('\n'),
+                        _ => {}
+                    },
+                    _ => {}
+                }
+            }
+            // Line breaks in the source are not part of the text
+            b'\r' | b'\n' => i += 1,
+            byte => {
+                if pending_skip > 0 {
+                    pending_skip -= 1;
+                } else if !group.skipped {
+                    text.push(windows_1252(byte));
+                }
+                i += 1;
+            }
+        }
+    }
+
+    Ok(text)
+}
+
+/// A control sequence following a backslash
+enum Control<'a> {
+    /// Control word with its optional numeric parameter, e.g. `\par` or `\fs24`
+    Word(&'a str, Option<i32>),
+    /// Hex-escaped code page byte, e.g. `\'e9`
+    Hex(u8),
+    /// Control symbol, e.g. `\{` or `\~`
+    Symbol(u8),
+}
+
+/// Parse the control sequence starting at `start`, right after a backslash
+///
+/// Returns the control and the position after it, including the single
+/// space that delimits a control word.
+fn control_word(bytes: &[u8], start: usize) -> (Control<'_>, usize) {
+    let Some(&first) = bytes.get(start) else {
+        return (Control::Symbol(b'\\'), start);
+    };
+
+    if first == b'\'' {
+        let hex = bytes
+            .get(start + 1..start + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        return match hex {
+            Some(byte) => (Control::Hex(byte), start + 3),
+            None => (Control::Symbol(first), start + 1),
+        };
+    }
+
+    if first == b'*' || !first.is_ascii_alphabetic() {
+        let control = if first == b'*' {
+            Control::Word("*", None)
+        } else {
+            Control::Symbol(first)
+        };
+        return (control, start + 1);
+    }
+
+    let mut end = start;
+    while end < bytes.len() && bytes[end].is_ascii_alphabetic() {
+        end += 1;
+    }
+    // ASCII letters only, so always valid UTF-8
+    let word = std::str::from_utf8(&bytes[start..end]).unwrap_or_default();
+
+    let number_start = end;
+    if bytes.get(end) == Some(&b'-') {
+        end += 1;
+    }
+    while end < bytes.len() && bytes[end].is_ascii_digit() {
+        end += 1;
+    }
+    let parameter = std::str::from_utf8(&bytes[number_start..end])
+        .ok()
+        .and_then(|number| number.parse().ok());
+
+    if bytes.get(end) == Some(&b' ') {
+        end += 1;
+    }
+
+    (Control::Word(word, parameter), end)
+}
+
+/// Decode a Windows-1252 byte, the code page of virtually all RTF files
+fn windows_1252(byte: u8) -> char {
+    let bytes = [byte];
+    let (decoded, _, _) = encoding_rs::WINDOWS_1252.decode(&bytes);
+    decoded
+        .chars()
+        .next()
+        .unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(rtf: &str) -> String {
+        extract_text(rtf.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn paragraphs_tabs_and_escaped_symbols_are_kept() {
+        assert_eq!(
+            text(r"{\rtf1\ansi Skills:\tab Rust\par C\{\}\\ and \'e9t\'e9\line end}"),
+            "Skills:\tRust\nC{}\\ and été\nend"
+        );
+    }
+
+    #[test]
+    fn unicode_escapes_drop_their_fallback_characters() {
+        assert_eq!(text(r"{\rtf1 caf\u233?s}"), "cafés");
+        assert_eq!(text(r"{\rtf1\uc2 \u8364??x}"), "€x");
+        assert_eq!(text(r"{\rtf1\uc0 \u8364 x}"), "€x");
+        assert_eq!(text(r"{\rtf1 caf\u233\'e9s}"), "cafés");
+        assert_eq!(text(r"{\rtf1 \u-3?}"), "\u{fffd}");
+    }
+
+    #[test]
+    fn unicode_skip_count_is_scoped_to_its_group() {
+        assert_eq!(text(r"{\rtf1 {\uc0 \u233 }\u233?}"), "éé");
+        // A group ending cancels fallback characters still to drop
+        assert_eq!(text(r"{\rtf1\uc3 {\u233?}ok}"), "éok");
+    }
+
+    #[test]
+    fn ignorable_and_metadata_destinations_are_skipped() {
+        assert_eq!(
+            text(
+                r"{\rtf1{\fonttbl{\f0\fswiss Arial;}}{\colortbl;\red0\green0\blue0;}{\*\generator Writer 1.0;}{\info{\title Resume}{\author Jane}}{\*\unknowndest hidden {\b nested}}Body}"
+            ),
+            "Body"
+        );
+    }
+
+    #[test]
+    fn binary_data_is_skipped() {
+        let mut rtf = br"{\rtf1 a{\pict\bin6 ".to_vec();
+        rtf.extend_from_slice(b"}{\\x\0{");
+        rtf.extend_from_slice(br"}b\bin3 {}c\bin99 d}");
+
+        assert_eq!(extract_text(&rtf).unwrap(), "ab");
+        assert_eq!(text(r"{\rtf1 a\bin0 b\bin-4 c}"), "abc");
+    }
+
+    #[test]
+    fn nested_groups_restore_the_outer_state() {
+        assert_eq!(text(r"{\rtf1 a{\b b{\i c}}d{\*\x e{f}}g}"), "abcdg");
+    }
+
+    #[test]
+    fn malformed_input_does_not_panic() {
+        assert!(extract_text(b"plain text").is_err());
+        assert!(extract_text(b"").is_err());
+        assert_eq!(text(r"{\rtf1 a}}b{"), "ab");
+        // A truncated hex escape drops the quote
+        assert_eq!(text(r"{\rtf1 a\'e"), "ae");
+        assert_eq!(text(r"{\rtf1 a\u99999999999 b"), "ab");
+        assert_eq!(text("{\\rtf1 a\\"), "a\\");
+    }
+}
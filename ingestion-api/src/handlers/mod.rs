@@ -1,7 +1,24 @@
-use crate::{error::ApiError, kafka, state::AppState};
-use axum::{Json, extract::State, http::StatusCode};
-use shared_types::DocumentPayload;
+use crate::{
+    batch,
+    error::ApiError,
+    extract, ids, kafka,
+    state::AppState,
+    upload,
+    validation::{self, Violation},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::{Multipart, Path, Query, State, rejection::JsonRejection},
+    http::{
+        HeaderMap, HeaderName, StatusCode,
+        header::{CONTENT_TYPE, LOCATION},
+    },
+};
+use serde::Deserialize;
+use shared_types::{BatchSubmitResponse, DocumentStatus, DocumentSubmission, SubmitResponse};
 use std::sync::Arc;
+use uuid::Uuid;
 
 /// Health check endpoint
 pub async fn health_check() -> StatusCode {
@@ -9,16 +26,148 @@ pub async fn health_check() -> StatusCode {
 }
 
 /// Submit resume/document handler
-/// Accepts JSON payload and publishes to Kafka
+/// Accepts JSON payload, validates it and publishes to Kafka, assigning an ID if none is given
 pub async fn submit_resume(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<DocumentPayload>,
-) -> Result<StatusCode, ApiError> {
-    let doc_id = payload.id.to_string();
-    tracing::info!(%doc_id, "Received document submission");
+    headers: HeaderMap,
+    submission: Result<Json<DocumentSubmission>, JsonRejection>,
+) -> Result<(StatusCode, [(HeaderName, String); 1], Json<SubmitResponse>), ApiError> {
+    let Json(submission) = submission.map_err(|rejection| match rejection.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => {
+            ApiError::InvalidDocument(vec![Violation::body_too_large(
+                state.config.validation.max_body_bytes,
+            )])
+        }
+        _ => ApiError::InvalidRequest(rejection.body_text()),
+    })?;
+    validation::validate(&submission, &state.config.validation)?;
+
+    let idempotency_key = ids::idempotency_key(&headers)?;
+    if let Some(key) = &idempotency_key {
+        state.idempotency_keys.claim(key, &submission)?;
+    }
+    let id = ids::document_id(submission.id, idempotency_key.as_ref());
+    tracing::info!(doc_id = %id, "Received document submission");
 
-    kafka::publish_document_event(&state.producer, &state.config, payload).await?;
+    kafka::publish_document_event(&state.producer, &state.config, submission.into_payload(id))
+        .await?;
 
     // Return 202 Accepted upon successful handoff to Kafka
+    Ok((
+        StatusCode::ACCEPTED,
+        [(LOCATION, ids::status_location(id))],
+        Json(SubmitResponse { id }),
+    ))
+}
+
+/// Upload document handler
+/// Accepts a multipart form with a PDF, DOCX, RTF, HTML or text file, extracts
+/// its text and publishes it like a JSON submission
+pub async fn upload_document(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<(StatusCode, [(HeaderName, String); 1], Json<SubmitResponse>), ApiError> {
+    let idempotency_key = ids::idempotency_key(&headers)?;
+    let submission = upload::read_submission(
+        multipart,
+        state.config.upload_max_bytes,
+        extract::Limits {
+            max_expanded_bytes: state.config.upload_max_expanded_bytes,
+            max_content_bytes: state.config.validation.max_content_bytes,
+        },
+    )
+    .await?;
+    validation::validate(&submission, &state.config.validation)?;
+
+    if let Some(key) = &idempotency_key {
+        state.idempotency_keys.claim(key, &submission)?;
+    }
+    let id = ids::document_id(submission.id, idempotency_key.as_ref());
+    tracing::info!(
+        doc_id = %id,
+        file_name = submission.metadata.file_name.as_deref().unwrap_or_default(),
+        content_length = submission.content.len(),
+        "Received document upload"
+    );
+
+    kafka::publish_document_event(&state.producer, &state.config, submission.into_payload(id))
+        .await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        [(LOCATION, ids::status_location(id))],
+        Json(SubmitResponse { id }),
+    ))
+}
+
+/// Query parameters of a deletion
+#[derive(Debug, Deserialize)]
+pub struct DeleteParams {
+    /// Version the deletion applies up to; the ingestion time when absent
+    pub version: Option<u64>,
+}
+
+/// Delete document handler
+/// Publishes a deletion that removes every indexed chunk of the document,
+/// and every submission of it whose version is not newer than the deletion
+pub async fn delete_document(
+    State(state): State<Arc<AppState>>,
+    Path(doc_id): Path<Uuid>,
+    Query(params): Query<DeleteParams>,
+) -> Result<StatusCode, ApiError> {
+    tracing::info!(%doc_id, "Received document deletion");
+
+    validation::validate_version(params.version)?;
+    kafka::publish_deletion_event(&state.producer, &state.config, doc_id, params.version).await?;
+
+    // Deletion is asynchronous, like ingestion
     Ok(StatusCode::ACCEPTED)
 }
+
+/// Document status handler
+/// Reports how far the document has progressed through the pipeline
+pub async fn get_document_status(
+    State(state): State<Arc<AppState>>,
+    Path(doc_id): Path<Uuid>,
+) -> Result<Json<DocumentStatus>, ApiError> {
+    state
+        .statuses
+        .get(doc_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("no status known for document {}", doc_id)))
+}
+
+/// Submit a batch of documents
+/// Accepts a JSON array, or an NDJSON stream when sent with
+/// `Content-Type: application/x-ndjson`, and reports a result per document
+pub async fn submit_batch(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<BatchSubmitResponse>, ApiError> {
+    let is_ndjson = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| {
+            content_type.starts_with("application/x-ndjson")
+                || content_type.starts_with("application/ndjson")
+        });
+
+    let idempotency_key = ids::idempotency_key(&headers)?;
+
+    let response = if is_ndjson {
+        batch::submit_ndjson(state, body, idempotency_key.as_ref()).await
+    } else {
+        let bytes = batch::read_json_array(body, state.config.batch_max_body_bytes).await?;
+        batch::submit_json_array(state, &bytes, idempotency_key.as_ref()).await?
+    };
+
+    tracing::info!(
+        accepted = response.accepted,
+        rejected = response.rejected,
+        "Processed batch submission"
+    );
+
+    Ok(Json(response))
+}
@@ -0,0 +1,242 @@
+use crate::error::ApiError;
+use axum::http::{HeaderMap, HeaderName, header::AUTHORIZATION};
+use shared_types::DocumentSubmission;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Header carrying a client-chosen key that makes a submission safe to retry
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Longest accepted idempotency key, in bytes
+const MAX_KEY_LEN: usize = 255;
+
+/// Namespace of the document IDs derived from idempotency keys
+///
+/// Requests without credentials use it directly; each credential derives its
+/// own namespace from it.
+const IDEMPOTENCY_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2b8e_4d3a_5f70_9e21_c4a8_b35d_07e6);
+
+/// Namespace of submission fingerprints
+const FINGERPRINT_NAMESPACE: Uuid = Uuid::from_u128(0x2d94_71c3_0b6e_4a58_8f13_e7c2_59a0_d41b);
+
+/// An idempotency key, scoped to the credential of the request that sent it
+///
+/// Two clients choosing the same key never map to the same document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyKey {
+    scope: Uuid,
+    key: String,
+}
+
+impl IdempotencyKey {
+    /// The key of one entry of a batch submitted under this key
+    ///
+    /// Retrying the same batch with the same key maps every entry to the same
+    /// document again, as long as the entries keep their positions.
+    pub fn entry(&self, index: usize) -> Self {
+        Self {
+            scope: self.scope,
+            key: format!("{}/{}", self.key, index),
+        }
+    }
+
+    /// Document ID derived from the key within its scope
+    fn id(&self) -> Uuid {
+        Uuid::new_v5(&self.scope, self.key.as_bytes())
+    }
+}
+
+/// Read and validate the `Idempotency-Key` header, if present
+///
+/// The key is scoped by the `Authorization` header; requests without one
+/// share a single anonymous scope.
+pub fn idempotency_key(headers: &HeaderMap) -> Result<Option<IdempotencyKey>, ApiError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY) else {
+        return Ok(None);
+    };
+
+    let key = value
+        .to_str()
+        .map_err(|_| ApiError::InvalidRequest("Idempotency-Key must be ASCII".to_string()))?;
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        return Err(ApiError::InvalidRequest(format!(
+            "Idempotency-Key must be 1 to {} characters long",
+            MAX_KEY_LEN
+        )));
+    }
+
+    let scope = match headers.get(AUTHORIZATION) {
+        Some(credential) => Uuid::new_v5(&IDEMPOTENCY_NAMESPACE, credential.as_bytes()),
+        None => IDEMPOTENCY_NAMESPACE,
+    };
+
+    Ok(Some(IdempotencyKey {
+        scope,
+        key: key.to_string(),
+    }))
+}
+
+/// The ID of a submitted document
+///
+/// A caller-provided ID always wins. Otherwise the ID is derived from the
+/// idempotency key, so a retried submission maps to the same document
+/// instead of creating a duplicate, or freshly generated without a key.
+pub fn document_id(provided: Option<Uuid>, idempotency_key: Option<&IdempotencyKey>) -> Uuid {
+    match (provided, idempotency_key) {
+        (Some(id), _) => id,
+        (None, Some(key)) => key.id(),
+        (None, None) => Uuid::new_v4(),
+    }
+}
+
+/// Where the status of a document can be polled
+pub fn status_location(id: Uuid) -> String {
+    format!("/documents/{}/status", id)
+}
+
+/// Recently used idempotency keys with the submission each was first used for
+///
+/// Lets a retry through only if it repeats the original submission, so a key
+/// reused for a different document is rejected instead of replacing it.
+/// Keys are remembered in memory by each API instance; once full, the least
+/// recently used key is forgotten.
+#[derive(Clone)]
+pub struct IdempotencyKeys {
+    inner: Arc<Mutex<Keys>>,
+}
+
+struct Keys {
+    capacity: usize,
+    /// Fingerprint of each key's submission with the sequence number of its latest use
+    fingerprints: HashMap<Uuid, (Uuid, u64)>,
+    /// Keys by sequence number of their latest use, oldest first
+    used: BTreeMap<u64, Uuid>,
+    next_sequence: u64,
+}
+
+impl IdempotencyKeys {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Keys {
+                capacity: capacity.max(1),
+                fingerprints: HashMap::new(),
+                used: BTreeMap::new(),
+                next_sequence: 0,
+            })),
+        }
+    }
+
+    /// Record that `key` is used for `submission`
+    ///
+    /// Fails if the key was already used for a different submission.
+    pub fn claim(
+        &self,
+        key: &IdempotencyKey,
+        submission: &DocumentSubmission,
+    ) -> Result<(), ApiError> {
+        let fingerprint = fingerprint(submission)?;
+        let mut keys = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let Keys {
+            capacity,
+            fingerprints,
+            used,
+            next_sequence,
+        } = &mut *keys;
+
+        let sequence = *next_sequence;
+        *next_sequence += 1;
+        let (claimed, last_use) = fingerprints
+            .entry(key.id())
+            .or_insert((fingerprint, sequence));
+        if *claimed != fingerprint {
+            return Err(ApiError::IdempotencyKeyReused(format!(
+                "Idempotency-Key {} was already used for a different document",
+                key.key
+            )));
+        }
+        used.remove(last_use);
+        *last_use = sequence;
+        used.insert(sequence, key.id());
+
+        while fingerprints.len() > *capacity {
+            let Some((_, evicted)) = used.pop_first() else {
+                break;
+            };
+            fingerprints.remove(&evicted);
+        }
+        Ok(())
+    }
+}
+
+/// Digest of everything a submission sends
+fn fingerprint(submission: &DocumentSubmission) -> Result<Uuid, ApiError> {
+    let bytes =
+        serde_json::to_vec(submission).map_err(|e| ApiError::SerializationError(e.to_string()))?;
+    Ok(Uuid::new_v5(&FINGERPRINT_NAMESPACE, &bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(key: &str, credential: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY, HeaderValue::from_str(key).unwrap());
+        if let Some(credential) = credential {
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(credential).unwrap());
+        }
+        headers
+    }
+
+    fn submission(content: &str) -> DocumentSubmission {
+        serde_json::from_value(serde_json::json!({ "content": content })).unwrap()
+    }
+
+    #[test]
+    fn same_key_from_different_credentials_maps_to_different_documents() {
+        let alice = idempotency_key(&headers("k1", Some("Bearer alice"))).unwrap();
+        let bob = idempotency_key(&headers("k1", Some("Bearer bob"))).unwrap();
+        let anonymous = idempotency_key(&headers("k1", None)).unwrap();
+
+        let alice_id = document_id(None, alice.as_ref());
+        assert_eq!(alice_id, document_id(None, alice.as_ref()));
+        assert_ne!(alice_id, document_id(None, bob.as_ref()));
+        assert_ne!(alice_id, document_id(None, anonymous.as_ref()));
+    }
+
+    #[test]
+    fn batch_entries_derive_distinct_ids() {
+        let key = idempotency_key(&headers("batch", None)).unwrap().unwrap();
+
+        assert_eq!(key.entry(0).id(), key.entry(0).id());
+        assert_ne!(key.entry(0).id(), key.entry(1).id());
+        assert_ne!(key.entry(0).id(), key.id());
+    }
+
+    #[test]
+    fn reused_key_with_a_different_submission_is_rejected() {
+        let keys = IdempotencyKeys::new(10);
+        let key = idempotency_key(&headers("k1", None)).unwrap().unwrap();
+
+        assert!(keys.claim(&key, &submission("first")).is_ok());
+        assert!(keys.claim(&key, &submission("first")).is_ok());
+        assert!(matches!(
+            keys.claim(&key, &submission("second")),
+            Err(ApiError::IdempotencyKeyReused(_))
+        ));
+    }
+
+    #[test]
+    fn least_recently_used_key_is_forgotten() {
+        let keys = IdempotencyKeys::new(1);
+        let first = idempotency_key(&headers("k1", None)).unwrap().unwrap();
+        let second = idempotency_key(&headers("k2", None)).unwrap().unwrap();
+
+        keys.claim(&first, &submission("first")).unwrap();
+        keys.claim(&second, &submission("second")).unwrap();
+
+        assert!(keys.claim(&first, &submission("other")).is_ok());
+    }
+}
@@ -1,11 +1,14 @@
 use crate::{config::Config, error::ApiError};
 use rdkafka::{
     config::ClientConfig,
+    consumer::StreamConsumer,
     producer::{FutureProducer, FutureRecord},
     util::Timeout,
 };
-use shared_types::{DocumentPayload, DocumentReceivedEvent};
-use std::time::Duration;
+use shared_runtime::{status, tombstone};
+use shared_types::{DocumentPayload, DocumentReceivedEvent, DocumentStage, DocumentStatusEvent};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
 
 /// Initialize and return a configured Kafka producer
 pub fn create_producer(config: &Config) -> Result<FutureProducer, rdkafka::error::KafkaError> {
@@ -15,15 +18,34 @@ pub fn create_producer(config: &Config) -> Result<FutureProducer, rdkafka::error
         .create()
 }
 
+/// Initialize a consumer that reads the whole status topic
+///
+/// The consumer belongs to no consumer group: every instance assigns itself
+/// all partitions from the earliest offset, so each one sees every status
+/// update, and no group is left behind on the broker. Nothing is committed.
+pub fn create_status_consumer(
+    config: &Config,
+) -> Result<StreamConsumer, rdkafka::error::KafkaError> {
+    ClientConfig::new()
+        .set("bootstrap.servers", config.kafka_broker.to_string())
+        .set("enable.auto.commit", "false")
+        .set("auto.offset.reset", "earliest")
+        .create()
+}
+
 /// Publish a document event to Kafka
 pub async fn publish_document_event(
     producer: &FutureProducer,
     config: &Config,
-    payload: DocumentPayload,
+    mut payload: DocumentPayload,
 ) -> Result<(), ApiError> {
-    let doc_id = payload.id.to_string();
+    let id = payload.id;
+    let doc_id = id.to_string();
+
+    // Downstream, an older version never overwrites a newer one
+    let version = *payload.version.get_or_insert_with(ingestion_version);
 
-    tracing::info!(%doc_id, "Publishing document to Kafka");
+    tracing::info!(%doc_id, version, "Publishing document to Kafka");
 
     // Wrap payload in event structure
     let event = DocumentReceivedEvent { payload };
@@ -45,5 +67,49 @@ pub async fn publish_document_event(
     })?;
 
     tracing::info!(%doc_id, "Successfully published to Kafka");
+
+    let received = DocumentStatusEvent::now(id, DocumentStage::Received, Some(version));
+    status::publish(producer, &config.status_topic, received);
+
+    Ok(())
+}
+
+/// Version for documents submitted without one: the ingestion time in microseconds
+fn ingestion_version() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+/// Publish a deletion tombstone for a document to Kafka
+///
+/// The record is keyed by the document ID and carries no payload, so it lands
+/// on the same partition as the document's submissions and is processed after
+/// them. Its version, the ingestion time unless the client gives one, travels
+/// in a header: submissions that are not newer are refused downstream, even
+/// when they arrive after the deletion.
+pub async fn publish_deletion_event(
+    producer: &FutureProducer,
+    config: &Config,
+    doc_id: Uuid,
+    version: Option<u64>,
+) -> Result<(), ApiError> {
+    let doc_id = doc_id.to_string();
+    let version = version.unwrap_or_else(ingestion_version);
+
+    tracing::info!(%doc_id, version, "Publishing deletion to Kafka");
+
+    let record = FutureRecord::<str, [u8]>::to(config.kafka_topic.as_str())
+        .key(&doc_id)
+        .headers(tombstone::headers(version));
+
+    let timeout = Timeout::After(Duration::from_secs(config.kafka_send_timeout_secs));
+    producer.send(record, timeout).await.map_err(|(e, _)| {
+        tracing::error!(%doc_id, error = %e, "Failed to publish deletion to Kafka");
+        ApiError::KafkaPublishError(e.to_string())
+    })?;
+
+    tracing::info!(%doc_id, "Successfully published deletion to Kafka");
     Ok(())
 }
@@ -1,14 +1,25 @@
+mod batch;
 mod config;
 mod error;
+mod extract;
 mod handlers;
+mod ids;
 mod kafka;
 mod routes;
 mod state;
+mod status;
+mod upload;
+mod validation;
 
 use config::Config;
+use rdkafka::producer::Producer;
+use rdkafka::util::Timeout;
+use shared_runtime::Shutdown;
 use state::AppState;
+use status::StatusStore;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -22,13 +33,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let server_host: IpAddr = config.server_host.parse()?;
     let server_port = config.server_port;
+    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    let flush_timeout = Duration::from_secs(config.kafka_send_timeout_secs);
 
     // Initialize Kafka producer
     let producer = kafka::create_producer(&config)?;
     tracing::info!("Kafka Producer initialized successfully");
 
+    let shutdown = Shutdown::listen();
+
+    // Keep the document status store up to date in the background
+    let statuses = StatusStore::new(config.status_max_documents);
+    let status_config = config.clone();
+    let status_store = statuses.clone();
+    let status_shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        if let Err(e) = status::run_consumer(status_config, status_store, status_shutdown).await {
+            tracing::error!(error = %e, "Status consumer stopped");
+        }
+    });
+
     // Create application state with config included
-    let state = Arc::new(AppState::new(producer, config));
+    let state = Arc::new(AppState::new(producer.clone(), config, statuses));
 
     // Build router with state and config
     let app = routes::create_router(state);
@@ -38,7 +64,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+
+    // Stop accepting connections on SIGTERM/SIGINT and let in-flight requests
+    // finish, but never for longer than the configured deadline
+    let signal = shutdown.clone();
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        signal.triggered().await;
+    });
+    tokio::select! {
+        result = server => result?,
+        _ = shutdown.deadline(shutdown_timeout) => {
+            tracing::warn!("Shutdown deadline exceeded, dropping open connections");
+        }
+    }
+
+    // Deliver everything already accepted before exiting
+    producer.flush(Timeout::After(flush_timeout))?;
+    tracing::info!("Ingestion API stopped");
 
     Ok(())
 }
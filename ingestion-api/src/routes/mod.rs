@@ -1,7 +1,8 @@
 use crate::{handlers, state::AppState};
 use axum::{
     Router,
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    routing::{delete, get, post},
 };
 use std::sync::Arc;
 
@@ -9,6 +10,19 @@ use std::sync::Arc;
 pub fn create_router(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/health", get(handlers::health_check))
-        .route("/submit", post(handlers::submit_resume))
+        .route(
+            "/submit",
+            post(handlers::submit_resume).layer(DefaultBodyLimit::max(
+                state.config.validation.max_body_bytes,
+            )),
+        )
+        .route("/submit/batch", post(handlers::submit_batch))
+        .route(
+            "/upload",
+            post(handlers::upload_document)
+                .layer(DefaultBodyLimit::max(state.config.upload_max_bytes)),
+        )
+        .route("/documents/{id}", delete(handlers::delete_document))
+        .route("/documents/{id}/status", get(handlers::get_document_status))
         .with_state(state)
 }
@@ -1,4 +1,6 @@
 use crate::config::Config;
+use crate::ids::IdempotencyKeys;
+use crate::status::StatusStore;
 use rdkafka::producer::FutureProducer;
 
 /// Shared application state containing dependencies
@@ -6,10 +8,17 @@ use rdkafka::producer::FutureProducer;
 pub struct AppState {
     pub producer: FutureProducer,
     pub config: Config,
+    pub statuses: StatusStore,
+    pub idempotency_keys: IdempotencyKeys,
 }
 
 impl AppState {
-    pub fn new(producer: FutureProducer, config: Config) -> Self {
-        Self { producer, config }
+    pub fn new(producer: FutureProducer, config: Config, statuses: StatusStore) -> Self {
+        Self {
+            producer,
+            idempotency_keys: IdempotencyKeys::new(config.idempotency_max_keys),
+            config,
+            statuses,
+        }
     }
 }
@@ -0,0 +1,284 @@
+use crate::config::Config;
+use crate::kafka;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::message::Message;
+use rdkafka::{Offset, TopicPartitionList};
+use shared_runtime::Shutdown;
+use shared_types::{DocumentStage, DocumentStatus, DocumentStatusEvent};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Delay between attempts to look up the partitions of the status topic
+const STATUS_METADATA_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// In-memory view of the processing status of recent documents
+///
+/// Rebuilt from the status topic at startup, so it covers the documents
+/// whose status updates the topic still retains. Holds at most `capacity`
+/// documents; beyond that the least recently updated record is dropped.
+#[derive(Clone)]
+pub struct StatusStore {
+    inner: Arc<RwLock<Records>>,
+}
+
+struct Records {
+    capacity: usize,
+    /// Each record with the sequence number of its latest update
+    statuses: HashMap<Uuid, (DocumentStatus, u64)>,
+    /// Documents by sequence number of their latest update, oldest first
+    updated: BTreeMap<u64, Uuid>,
+    next_sequence: u64,
+}
+
+impl StatusStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Records {
+                capacity: capacity.max(1),
+                statuses: HashMap::new(),
+                updated: BTreeMap::new(),
+                next_sequence: 0,
+            })),
+        }
+    }
+
+    /// Current status of a document, if any update about it has been seen
+    pub fn get(&self, doc_id: Uuid) -> Option<DocumentStatus> {
+        let records = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        records
+            .statuses
+            .get(&doc_id)
+            .map(|(status, _)| status.clone())
+    }
+
+    fn apply(&self, event: &DocumentStatusEvent) {
+        let mut records = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let Records {
+            capacity,
+            statuses,
+            updated,
+            next_sequence,
+        } = &mut *records;
+
+        let sequence = *next_sequence;
+        *next_sequence += 1;
+        let (status, last_update) = statuses
+            .entry(event.id)
+            .or_insert_with(|| (DocumentStatus::default(), sequence));
+        updated.remove(last_update);
+        *last_update = sequence;
+        updated.insert(sequence, event.id);
+        apply(status, event);
+
+        while statuses.len() > *capacity {
+            let Some((_, evicted)) = updated.pop_first() else {
+                break;
+            };
+            statuses.remove(&evicted);
+        }
+    }
+}
+
+/// Feed status updates from every service into the store until shutdown
+pub async fn run_consumer(
+    config: Config,
+    store: StatusStore,
+    shutdown: Shutdown,
+) -> Result<(), rdkafka::error::KafkaError> {
+    let consumer = kafka::create_status_consumer(&config)?;
+    let Some(partitions) = status_partitions(&consumer, &config, &shutdown).await else {
+        return Ok(());
+    };
+    consumer.assign(&partitions)?;
+
+    tracing::info!(
+        topic = %config.status_topic,
+        partitions = partitions.count(),
+        "Status consumer started"
+    );
+
+    loop {
+        let received = tokio::select! {
+            biased;
+            _ = shutdown.triggered() => break,
+            received = consumer.recv() => received,
+        };
+        let message = match received {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!(error = %e, "Kafka receive error");
+                continue;
+            }
+        };
+
+        match message
+            .payload()
+            .map(serde_json::from_slice::<DocumentStatusEvent>)
+        {
+            Some(Ok(event)) => store.apply(&event),
+            Some(Err(e)) => tracing::warn!(error = %e, "Skipping malformed status update"),
+            None => tracing::warn!("Skipping status update without payload"),
+        }
+    }
+
+    tracing::info!("Status consumer stopped");
+
+    Ok(())
+}
+
+/// Every partition of the status topic, read from the beginning
+///
+/// The consumer has no group, so partitions are assigned by hand. Waits for
+/// the topic to exist, and gives up with `None` on shutdown. Partitions added
+/// to the topic later are only read after a restart.
+async fn status_partitions(
+    consumer: &StreamConsumer,
+    config: &Config,
+    shutdown: &Shutdown,
+) -> Option<TopicPartitionList> {
+    let timeout = Duration::from_secs(config.kafka_send_timeout_secs);
+    loop {
+        match consumer.fetch_metadata(Some(&config.status_topic), timeout) {
+            Ok(metadata) => {
+                let mut partitions = TopicPartitionList::new();
+                for topic in metadata.topics() {
+                    for partition in topic.partitions() {
+                        if let Err(e) = partitions.add_partition_offset(
+                            topic.name(),
+                            partition.id(),
+                            Offset::Beginning,
+                        ) {
+                            tracing::warn!(error = %e, "Failed to add status partition");
+                        }
+                    }
+                }
+                if partitions.count() > 0 {
+                    return Some(partitions);
+                }
+                tracing::warn!(topic = %config.status_topic, "Status topic has no partitions yet");
+            }
+            Err(e) => tracing::warn!(error = %e, "Failed to fetch status topic metadata"),
+        }
+
+        tokio::select! {
+            _ = shutdown.triggered() => return None,
+            _ = tokio::time::sleep(STATUS_METADATA_RETRY_DELAY) => {}
+        }
+    }
+}
+
+/// Fold a status update into a document's record
+///
+/// Updates about an older version than the recorded one are stale and
+/// ignored. Any update about a newer version starts the record over, and so
+/// does a submission while no version is recorded. The API reports a
+/// submission only once Kafka acknowledged it, so the workers may report the
+/// same version first; its late `Received` then only sets `received_at`.
+fn apply(status: &mut DocumentStatus, event: &DocumentStatusEvent) {
+    let newer = match (event.version, status.version) {
+        (Some(version), Some(current)) if version < current => return,
+        (Some(version), Some(current)) => version > current,
+        (_, None) => event.stage == DocumentStage::Received,
+        (None, Some(_)) => false,
+    };
+    if newer {
+        *status = DocumentStatus::default();
+    }
+
+    status.id = event.id;
+    if event.version.is_some() {
+        status.version = event.version;
+    }
+    match event.stage {
+        DocumentStage::Received => status.received_at = Some(event.at),
+        DocumentStage::Vectorized => status.vectorized_at = Some(event.at),
+        DocumentStage::Indexed => status.indexed_at = Some(event.at),
+        DocumentStage::Failed => {
+            status.failed_at = Some(event.at);
+            status.reason = event.reason.clone();
+        }
+        DocumentStage::Deleted => status.deleted_at = Some(event.at),
+    }
+
+    // A failure only counts until a later attempt gets further (e.g. a
+    // replay from the dead-letter topic)
+    let progressed_at = status.indexed_at.max(status.vectorized_at);
+    status.stage = if status.deleted_at.is_some() {
+        DocumentStage::Deleted
+    } else if status.failed_at > progressed_at {
+        DocumentStage::Failed
+    } else if status.indexed_at.is_some() {
+        DocumentStage::Indexed
+    } else if status.vectorized_at.is_some() {
+        DocumentStage::Vectorized
+    } else {
+        DocumentStage::Received
+    };
+    status.searchable = status.indexed_at.is_some() && status.deleted_at.is_none();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: Uuid, stage: DocumentStage, version: u64) -> DocumentStatusEvent {
+        DocumentStatusEvent::now(id, stage, Some(version))
+    }
+
+    #[test]
+    fn stages_progress_and_stale_updates_are_ignored() {
+        let store = StatusStore::new(10);
+        let id = Uuid::new_v4();
+
+        store.apply(&event(id, DocumentStage::Received, 2));
+        store.apply(&event(id, DocumentStage::Indexed, 2));
+        store.apply(&event(id, DocumentStage::Deleted, 1));
+
+        let status = store.get(id).unwrap();
+        assert_eq!(status.stage, DocumentStage::Indexed);
+        assert!(status.searchable);
+        assert_eq!(status.version, Some(2));
+    }
+
+    #[test]
+    fn late_received_keeps_the_progress_of_its_version() {
+        let store = StatusStore::new(10);
+        let id = Uuid::new_v4();
+
+        store.apply(&event(id, DocumentStage::Vectorized, 2));
+        store.apply(&event(id, DocumentStage::Indexed, 2));
+        store.apply(&event(id, DocumentStage::Received, 2));
+
+        let status = store.get(id).unwrap();
+        assert_eq!(status.stage, DocumentStage::Indexed);
+        assert!(status.searchable);
+        assert!(status.received_at.is_some() && status.vectorized_at.is_some());
+
+        // A newer submission starts over
+        store.apply(&event(id, DocumentStage::Received, 3));
+
+        let status = store.get(id).unwrap();
+        assert_eq!(status.stage, DocumentStage::Received);
+        assert_eq!(status.version, Some(3));
+        assert!(!status.searchable);
+        assert!(status.indexed_at.is_none());
+    }
+
+    #[test]
+    fn least_recently_updated_documents_are_evicted() {
+        let store = StatusStore::new(2);
+        let [first, second, third] = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+
+        store.apply(&event(first, DocumentStage::Received, 1));
+        store.apply(&event(second, DocumentStage::Received, 1));
+        // Updating the first document makes the second the oldest
+        store.apply(&event(first, DocumentStage::Vectorized, 1));
+        store.apply(&event(third, DocumentStage::Received, 1));
+
+        assert!(store.get(first).is_some());
+        assert!(store.get(second).is_none());
+        assert!(store.get(third).is_some());
+    }
+}
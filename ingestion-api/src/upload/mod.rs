@@ -0,0 +1,124 @@
+use crate::error::ApiError;
+use crate::extract::{self, ExtractError, Format, Limits};
+use crate::validation::Violation;
+use axum::extract::Multipart;
+use axum::extract::multipart::MultipartError;
+use axum::http::StatusCode;
+use shared_types::{DocumentMetadata, DocumentSubmission};
+use uuid::Uuid;
+
+/// The file part of an upload form
+struct UploadedFile {
+    file_name: Option<String>,
+    content_type: Option<String>,
+    bytes: Vec<u8>,
+}
+
+/// Read an upload form and extract the text of its file
+///
+/// Expects a `file` part plus optional `id`, `metadata` (JSON) and
+/// `version` parts, mirroring the fields of a JSON submission. The file's
+/// name and MIME type are recorded in the document metadata. A form larger
+/// than `max_bytes` is rejected as a validation failure; a compressed file
+/// expanding beyond its limit, or text beyond the content limit, with 413.
+pub async fn read_submission(
+    mut multipart: Multipart,
+    max_bytes: usize,
+    limits: Limits,
+) -> Result<DocumentSubmission, ApiError> {
+    let invalid_form = |e: MultipartError| match e.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => {
+            ApiError::InvalidDocument(vec![Violation::body_too_large(max_bytes)])
+        }
+        _ => ApiError::InvalidRequest(format!("invalid upload form: {}", e.body_text())),
+    };
+
+    let mut file = None;
+    let mut id = None;
+    let mut metadata = DocumentMetadata::default();
+    let mut version = None;
+
+    while let Some(field) = multipart.next_field().await.map_err(invalid_form)? {
+        match field.name().unwrap_or_default() {
+            "file" => {
+                let file_name = field.file_name().map(str::to_string);
+                let content_type = field.content_type().map(str::to_string);
+                let bytes = field.bytes().await.map_err(invalid_form)?;
+                file = Some(UploadedFile {
+                    file_name,
+                    content_type,
+                    bytes: bytes.to_vec(),
+                });
+            }
+            "id" => {
+                let value = field.text().await.map_err(invalid_form)?;
+                id =
+                    Some(Uuid::parse_str(value.trim()).map_err(|e| {
+                        ApiError::InvalidRequest(format!("invalid id field: {}", e))
+                    })?);
+            }
+            "metadata" => {
+                let value = field.bytes().await.map_err(invalid_form)?;
+                metadata = serde_json::from_slice(&value).map_err(|e| {
+                    ApiError::InvalidRequest(format!("invalid metadata field: {}", e))
+                })?;
+            }
+            "version" => {
+                let value = field.text().await.map_err(invalid_form)?;
+                version = Some(value.trim().parse().map_err(|e| {
+                    ApiError::InvalidRequest(format!("invalid version field: {}", e))
+                })?);
+            }
+            name => tracing::debug!(field = %name, "Ignoring unknown upload form field"),
+        }
+    }
+
+    let file = file
+        .ok_or_else(|| ApiError::InvalidRequest("upload form has no 'file' field".to_string()))?;
+
+    let format = Format::detect(file.content_type.as_deref(), file.file_name.as_deref())
+        .ok_or_else(|| {
+            ApiError::UnsupportedMediaType(format!(
+                "cannot extract text from {} (supported: PDF, DOCX, RTF, HTML, plain text)",
+                file.content_type
+                    .as_deref()
+                    .or(file.file_name.as_deref())
+                    .unwrap_or("a file without a name or content type")
+            ))
+        })?;
+
+    let content = extract_content(format, file.bytes, limits).await?;
+
+    metadata.file_name = file.file_name;
+    metadata.mime_type = Some(format.mime_type().to_string());
+
+    Ok(DocumentSubmission {
+        id,
+        content,
+        metadata,
+        version,
+    })
+}
+
+/// Extract text off the async runtime, since parsing a large file is CPU-bound
+///
+/// A panic inside an extractor (malformed input can trip the PDF parser)
+/// is reported as an unreadable file rather than taking the request down.
+async fn extract_content(
+    format: Format,
+    bytes: Vec<u8>,
+    limits: Limits,
+) -> Result<String, ApiError> {
+    tokio::task::spawn_blocking(move || extract::extract_text(format, &bytes, limits))
+        .await
+        .map_err(|_| {
+            ApiError::UnprocessableDocument(format!(
+                "{} file could not be parsed",
+                format.mime_type()
+            ))
+        })?
+        .map_err(|e| match e {
+            ExtractError::Unreadable(reason) => ApiError::UnprocessableDocument(reason),
+            ExtractError::TooLarge(reason) => ApiError::PayloadTooLarge(reason),
+        })
+}
@@ -0,0 +1,253 @@
+use crate::config::ValidationConfig;
+use crate::error::ApiError;
+use serde::Serialize;
+use shared_types::{DocumentSubmission, MAX_DOCUMENT_VERSION};
+
+/// A validation rule a request can fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// Too little text to be worth indexing
+    MinLength,
+    /// Too much text for a single Kafka message
+    MaxLength,
+    /// Binary data rather than text
+    Text,
+    /// Request body larger than the endpoint accepts
+    MaxBodySize,
+    /// Version larger than the index can store
+    Version,
+}
+
+impl Rule {
+    /// Whether failing the rule means the request was too large (413) rather than invalid (400)
+    pub fn is_size_limit(&self) -> bool {
+        matches!(self, Rule::MaxLength | Rule::MaxBodySize)
+    }
+}
+
+/// One failed rule, reported in the error body
+#[derive(Debug, Clone, Serialize)]
+pub struct Violation {
+    pub field: String,
+    pub rule: Rule,
+    pub message: String,
+}
+
+impl Violation {
+    fn new(field: &str, rule: Rule, message: String) -> Self {
+        Self {
+            field: field.to_string(),
+            rule,
+            message,
+        }
+    }
+
+    /// The request body exceeded `limit` bytes
+    pub fn body_too_large(limit: usize) -> Self {
+        Self::new(
+            "body",
+            Rule::MaxBodySize,
+            format!("request body must be at most {} bytes", limit),
+        )
+    }
+}
+
+/// Check a submission against the configured rules before it is published
+///
+/// Every rule is evaluated so the client sees all problems at once. Fails
+/// with `ApiError::InvalidDocument` listing the violations.
+pub fn validate(
+    submission: &DocumentSubmission,
+    config: &ValidationConfig,
+) -> Result<(), ApiError> {
+    let mut violations = content_violations(&submission.content, config);
+    violations.extend(version_violation(submission.version));
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::InvalidDocument(violations))
+    }
+}
+
+/// Check the version a deletion applies up to
+pub fn validate_version(version: Option<u64>) -> Result<(), ApiError> {
+    match version_violation(version) {
+        Some(violation) => Err(ApiError::InvalidDocument(vec![violation])),
+        None => Ok(()),
+    }
+}
+
+/// Versions are stored in the index as signed 64-bit integers
+fn version_violation(version: Option<u64>) -> Option<Violation> {
+    let version = version.filter(|version| *version > MAX_DOCUMENT_VERSION)?;
+    Some(Violation::new(
+        "version",
+        Rule::Version,
+        format!(
+            "version must be at most {}, got {}",
+            MAX_DOCUMENT_VERSION, version
+        ),
+    ))
+}
+
+fn content_violations(content: &str, config: &ValidationConfig) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    // Checked first and cheaply, so a huge body is not scanned character by character
+    if content.len() > config.max_content_bytes {
+        violations.push(Violation::new(
+            "content",
+            Rule::MaxLength,
+            format!(
+                "content must be at most {} bytes, got {}",
+                config.max_content_bytes,
+                content.len()
+            ),
+        ));
+        return violations;
+    }
+
+    let chars = content.trim().chars().count();
+    if chars < config.min_content_chars {
+        violations.push(Violation::new(
+            "content",
+            Rule::MinLength,
+            format!(
+                "content must have at least {} characters besides surrounding whitespace, got {}",
+                config.min_content_chars, chars
+            ),
+        ));
+    }
+
+    if content.contains('\0') {
+        violations.push(Violation::new(
+            "content",
+            Rule::Text,
+            "content must not contain NUL characters".to_string(),
+        ));
+    } else if chars > 0 {
+        let non_text = content.chars().filter(|c| is_non_text(*c)).count();
+        let ratio = non_text as f64 / content.chars().count() as f64;
+        if ratio > config.max_non_text_ratio {
+            violations.push(Violation::new(
+                "content",
+                Rule::Text,
+                format!(
+                    "content looks like binary data: {:.1}% control or replacement characters, at most {:.1}% allowed",
+                    ratio * 100.0,
+                    config.max_non_text_ratio * 100.0
+                ),
+            ));
+        }
+    }
+
+    violations
+}
+
+/// Control characters other than whitespace, and the U+FFFD left behind by lossy decoding
+fn is_non_text(c: char) -> bool {
+    (c.is_control() && !matches!(c, '\n' | '\r' | '\t')) || c == char::REPLACEMENT_CHARACTER
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(content: &str, config: &ValidationConfig) -> Vec<Rule> {
+        content_violations(content, config)
+            .into_iter()
+            .map(|v| v.rule)
+            .collect()
+    }
+
+    #[test]
+    fn plain_text_passes() {
+        let config = ValidationConfig::default();
+
+        assert!(rules("Rust engineer\n\twith Kafka experience\r\n", &config).is_empty());
+    }
+
+    #[test]
+    fn blank_content_is_too_short() {
+        let config = ValidationConfig::default();
+
+        assert_eq!(rules("", &config), vec![Rule::MinLength]);
+        assert_eq!(rules(" \n\t ", &config), vec![Rule::MinLength]);
+    }
+
+    #[test]
+    fn minimum_counts_characters_not_bytes() {
+        let config = ValidationConfig {
+            min_content_chars: 4,
+            ..ValidationConfig::default()
+        };
+
+        assert!(rules("café", &config).is_empty());
+        assert_eq!(rules("  ab  ", &config), vec![Rule::MinLength]);
+    }
+
+    #[test]
+    fn oversized_content_stops_further_checks() {
+        let config = ValidationConfig {
+            max_content_bytes: 8,
+            ..ValidationConfig::default()
+        };
+        let violations = content_violations("\0\0\0\0\0\0\0\0\0", &config);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule, Rule::MaxLength);
+        assert!(violations[0].rule.is_size_limit());
+    }
+
+    #[test]
+    fn nul_bytes_are_binary() {
+        let config = ValidationConfig::default();
+
+        assert_eq!(rules("text\0more text", &config), vec![Rule::Text]);
+    }
+
+    #[test]
+    fn control_characters_beyond_the_ratio_are_binary() {
+        let config = ValidationConfig {
+            max_non_text_ratio: 0.1,
+            ..ValidationConfig::default()
+        };
+
+        assert!(rules("abcdefghijklmnopqrs\u{7}", &config).is_empty());
+        assert_eq!(rules("abcdefgh\u{7}\u{7}", &config), vec![Rule::Text]);
+        assert_eq!(rules("abcdefgh\u{FFFD}\u{FFFD}", &config), vec![Rule::Text]);
+    }
+
+    #[test]
+    fn every_failed_rule_is_reported() {
+        let config = ValidationConfig {
+            min_content_chars: 10,
+            ..ValidationConfig::default()
+        };
+
+        assert_eq!(
+            rules("\u{1}\u{2}\u{3}", &config),
+            vec![Rule::MinLength, Rule::Text]
+        );
+    }
+
+    #[test]
+    fn body_limit_is_a_size_violation() {
+        let violation = Violation::body_too_large(1024);
+
+        assert_eq!(violation.field, "body");
+        assert!(violation.rule.is_size_limit());
+        assert!(!Rule::Text.is_size_limit());
+    }
+
+    #[test]
+    fn versions_beyond_i64_are_rejected() {
+        assert!(version_violation(None).is_none());
+        assert!(version_violation(Some(MAX_DOCUMENT_VERSION)).is_none());
+
+        let violation = version_violation(Some(MAX_DOCUMENT_VERSION + 1)).unwrap();
+        assert_eq!(violation.rule, Rule::Version);
+        assert!(!violation.rule.is_size_limit());
+    }
+}
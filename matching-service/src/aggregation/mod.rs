@@ -0,0 +1,104 @@
+use crate::error::MatcherError;
+use qdrant_client::qdrant::value::Kind;
+use qdrant_client::qdrant::{PointGroup, ScoredPoint, group_id};
+use shared_types::{MatchResult, MatchedChunk, ScoreAggregation};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Payload field shared by all chunks of a document, used to group hits
+pub const GROUP_BY_FIELD: &str = "document_id";
+
+/// Payload field holding the chunk text, only loaded for reranking
+pub const TEXT_FIELD: &str = "chunk_text";
+
+/// Reject aggregation settings that cannot produce a score
+pub fn validate(strategy: &ScoreAggregation) -> Result<(), MatcherError> {
+    match strategy {
+        ScoreAggregation::MeanTopN { n: 0 } => Err(MatcherError::InvalidQuery(
+            "mean_top_n aggregation requires n >= 1".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// How many chunks Qdrant must return per document for the strategy
+///
+/// `mean_top_n` needs at least `n` chunks; the others use the configured amount.
+pub fn group_size(strategy: &ScoreAggregation, chunks_per_document: u32) -> u32 {
+    match strategy {
+        ScoreAggregation::MeanTopN { n } => (*n).max(chunks_per_document),
+        _ => chunks_per_document,
+    }
+    .max(1)
+}
+
+/// Turn per-document chunk groups into ranked document results
+///
+/// Groups arrive ordered by their best chunk; strategies other than `max`
+/// can change that order, so results are re-sorted by aggregated score.
+pub fn to_match_results(groups: Vec<PointGroup>, strategy: &ScoreAggregation) -> Vec<MatchResult> {
+    let mut results: Vec<MatchResult> = groups
+        .into_iter()
+        .filter_map(|group| to_match_result(group, strategy))
+        .collect();
+
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
+}
+
+fn to_match_result(group: PointGroup, strategy: &ScoreAggregation) -> Option<MatchResult> {
+    let id = match group.id.and_then(|id| id.kind) {
+        Some(group_id::Kind::StringValue(id)) => Uuid::from_str(&id).ok()?,
+        _ => return None,
+    };
+    if group.hits.is_empty() {
+        return None;
+    }
+
+    let scores: Vec<f32> = group.hits.iter().map(|hit| hit.score).collect();
+    let chunks = group.hits.iter().filter_map(to_matched_chunk).collect();
+
+    Some(MatchResult {
+        id,
+        score: aggregate(strategy, &scores),
+        retrieval_score: None,
+        rerank_score: None,
+        chunks,
+    })
+}
+
+/// Combine chunk scores, which Qdrant returns best first
+fn aggregate(strategy: &ScoreAggregation, scores: &[f32]) -> f32 {
+    match strategy {
+        ScoreAggregation::Max => scores[0],
+        ScoreAggregation::MeanTopN { n } => {
+            let top = &scores[..scores.len().min(*n as usize)];
+            top.iter().sum::<f32>() / top.len() as f32
+        }
+        ScoreAggregation::Sum => scores.iter().sum(),
+    }
+}
+
+fn to_matched_chunk(hit: &ScoredPoint) -> Option<MatchedChunk> {
+    Some(MatchedChunk {
+        index: payload_usize(hit, "chunk_index")?,
+        start: payload_usize(hit, "chunk_start")?,
+        end: payload_usize(hit, "chunk_end")?,
+        score: hit.score,
+        text: payload_string(hit, TEXT_FIELD),
+    })
+}
+
+fn payload_usize(hit: &ScoredPoint, key: &str) -> Option<usize> {
+    match hit.payload.get(key).and_then(|value| value.kind.as_ref()) {
+        Some(Kind::IntegerValue(i)) => usize::try_from(*i).ok(),
+        _ => None,
+    }
+}
+
+fn payload_string(hit: &ScoredPoint, key: &str) -> Option<String> {
+    match hit.payload.get(key).and_then(|value| value.kind.as_ref()) {
+        Some(Kind::StringValue(s)) => Some(s.clone()),
+        _ => None,
+    }
+}
@@ -0,0 +1,61 @@
+use crate::config::BatchConfig;
+use crate::error::MatcherError;
+use serde::Deserialize;
+use shared_types::{BatchMatchResponse, BatchMatchResult, MatchQuery, MatchResponse};
+
+/// Request payload of a batch match
+///
+/// Queries stay raw JSON until each is parsed on its own, so a malformed
+/// query only fails itself instead of the whole batch.
+#[derive(Debug, Deserialize)]
+pub struct BatchMatchQuery {
+    pub queries: Vec<serde_json::Value>,
+}
+
+/// Reject batches larger than the service accepts
+pub fn validate(batch: &BatchMatchQuery, config: &BatchConfig) -> Result<(), MatcherError> {
+    if batch.queries.len() > config.max_queries {
+        return Err(MatcherError::InvalidQuery(format!(
+            "at most {} queries are allowed per batch, got {}",
+            config.max_queries,
+            batch.queries.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Parse one entry of the batch as a match query
+pub fn parse(entry: serde_json::Value) -> Result<MatchQuery, MatcherError> {
+    serde_json::from_value(entry).map_err(|e| MatcherError::InvalidQuery(e.to_string()))
+}
+
+/// Report the outcome of one query the way `/match` would
+pub fn result(index: usize, outcome: Result<MatchResponse, MatcherError>) -> BatchMatchResult {
+    match outcome {
+        Ok(response) => BatchMatchResult {
+            index,
+            status: 200,
+            response: Some(response),
+            error: None,
+        },
+        Err(e) => {
+            tracing::warn!(index, "Batch query failed: {}", e);
+            BatchMatchResult {
+                index,
+                status: e.status().as_u16(),
+                response: None,
+                error: Some(e.message().to_string()),
+            }
+        }
+    }
+}
+
+/// Count the outcomes of a batch
+pub fn summarize(results: Vec<BatchMatchResult>) -> BatchMatchResponse {
+    let succeeded = results.iter().filter(|r| r.response.is_some()).count();
+    BatchMatchResponse {
+        succeeded,
+        failed: results.len() - succeeded,
+        results,
+    }
+}
@@ -1,4 +1,6 @@
 use serde::Deserialize;
+use shared_embedder::{CrossEncoderConfig, EmbedderConfig};
+use shared_types::{Retrieval, ScoreAggregation};
 use std::fs;
 
 /// Configuration for the matching service
@@ -8,6 +10,103 @@ pub struct Config {
     pub collection_name: String,
     pub server_host: String,
     pub server_port: u16,
+    pub embedder: EmbedderConfig,
+    pub aggregation: ScoreAggregation,
+    pub chunks_per_document: u32,
+    /// Default retrieval mode, used when a query does not choose one
+    pub retrieval: Retrieval,
+    pub hybrid: HybridConfig,
+    /// Optional cross-encoder stage reranking the retrieved documents
+    #[serde(default)]
+    pub rerank: Option<RerankConfig>,
+    pub paging: PagingConfig,
+    pub similar: SimilarConfig,
+    pub batch: BatchConfig,
+    pub shutdown_timeout_secs: u64,
+}
+
+/// Limits on batch match requests
+#[derive(Debug, Deserialize, Clone)]
+pub struct BatchConfig {
+    /// Most queries one batch request may carry
+    pub max_queries: usize,
+    /// Queries of a batch searched concurrently; each may run several
+    /// Qdrant requests and a cross-encoder pass
+    pub max_in_flight: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_queries: 100,
+            max_in_flight: 8,
+        }
+    }
+}
+
+/// Limits on more-like-this queries
+#[derive(Debug, Deserialize, Clone)]
+pub struct SimilarConfig {
+    /// Most positive and negative documents a query may name together
+    pub max_seed_documents: usize,
+}
+
+impl Default for SimilarConfig {
+    fn default() -> Self {
+        Self {
+            max_seed_documents: 20,
+        }
+    }
+}
+
+/// Limits on paging through results and counting them
+#[derive(Debug, Deserialize, Clone)]
+pub struct PagingConfig {
+    /// Largest accepted `top_k`, of `/match`, `/match/batch` and `/match/similar`
+    pub max_top_k: u64,
+    /// Largest accepted `offset`; every page ranks all documents before it
+    pub max_offset: u64,
+    /// `total_above_threshold` stops counting at this many documents
+    pub count_limit: u64,
+}
+
+impl Default for PagingConfig {
+    fn default() -> Self {
+        Self {
+            max_top_k: 100,
+            max_offset: 1000,
+            count_limit: 1000,
+        }
+    }
+}
+
+/// Cross-encoder reranking of the best retrieved documents
+#[derive(Debug, Deserialize, Clone)]
+pub struct RerankConfig {
+    pub cross_encoder: CrossEncoderConfig,
+    /// Documents retrieved and rescored per query, the same for every page;
+    /// reranked queries cannot page past them. Every chunk returned for them
+    /// is one model input, so the cost grows with this times `chunks_per_document`.
+    pub candidates: u64,
+}
+
+/// Keyword (sparse) retrieval and its fusion with dense retrieval
+#[derive(Debug, Deserialize, Clone)]
+pub struct HybridConfig {
+    /// Named sparse vector of the collection holding BM25 chunk vectors
+    pub sparse_vector_name: String,
+    /// Each search of a fused query ranks this many times `top_k` documents,
+    /// so documents ranked lower by one search can still make the final cut
+    pub candidate_multiplier: u64,
+}
+
+impl Default for HybridConfig {
+    fn default() -> Self {
+        Self {
+            sparse_vector_name: "bm25".to_string(),
+            candidate_multiplier: 3,
+        }
+    }
 }
 
 impl Config {
@@ -25,6 +124,16 @@ impl Config {
             collection_name: "resumes".to_string(),
             server_host: "0.0.0.0".to_string(),
             server_port: 3001,
+            embedder: EmbedderConfig::default(),
+            aggregation: ScoreAggregation::default(),
+            chunks_per_document: 3,
+            retrieval: Retrieval::default(),
+            hybrid: HybridConfig::default(),
+            rerank: None,
+            paging: PagingConfig::default(),
+            similar: SimilarConfig::default(),
+            batch: BatchConfig::default(),
+            shutdown_timeout_secs: 30,
         }
     }
 }
@@ -6,10 +6,13 @@ use axum::{
 use serde_json::json;
 
 /// Custom error types for the matching service
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum MatcherError {
     VectorizationError(String),
     QdrantSearchError(String),
+    InvalidFilter(String),
+    InvalidQuery(String),
+    DocumentNotFound(String),
     InternalError(String),
 }
 
@@ -18,6 +21,9 @@ impl std::fmt::Display for MatcherError {
         match self {
             MatcherError::VectorizationError(e) => write!(f, "Vectorization error: {}", e),
             MatcherError::QdrantSearchError(e) => write!(f, "Qdrant search error: {}", e),
+            MatcherError::InvalidFilter(e) => write!(f, "Invalid filter: {}", e),
+            MatcherError::InvalidQuery(e) => write!(f, "Invalid query: {}", e),
+            MatcherError::DocumentNotFound(e) => write!(f, "Document not found: {}", e),
             MatcherError::InternalError(e) => write!(f, "Internal error: {}", e),
         }
     }
@@ -25,18 +31,49 @@ impl std::fmt::Display for MatcherError {
 
 impl std::error::Error for MatcherError {}
 
+impl MatcherError {
+    /// HTTP status the error is reported with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            MatcherError::VectorizationError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            MatcherError::QdrantSearchError(_) => StatusCode::BAD_GATEWAY,
+            MatcherError::InvalidFilter(_) => StatusCode::BAD_REQUEST,
+            MatcherError::InvalidQuery(_) => StatusCode::BAD_REQUEST,
+            MatcherError::DocumentNotFound(_) => StatusCode::NOT_FOUND,
+            MatcherError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Message returned to the client, without the variant prefix
+    pub fn message(&self) -> &str {
+        match self {
+            MatcherError::VectorizationError(e)
+            | MatcherError::QdrantSearchError(e)
+            | MatcherError::InvalidFilter(e)
+            | MatcherError::InvalidQuery(e)
+            | MatcherError::DocumentNotFound(e)
+            | MatcherError::InternalError(e) => e,
+        }
+    }
+}
+
+impl From<shared_embedder::EmbedderError> for MatcherError {
+    fn from(err: shared_embedder::EmbedderError) -> Self {
+        match err {
+            shared_embedder::EmbedderError::InferenceError(e) => {
+                MatcherError::VectorizationError(e)
+            }
+            shared_embedder::EmbedderError::ModelInitError(e) => MatcherError::InternalError(e),
+        }
+    }
+}
+
 impl IntoResponse for MatcherError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match &self {
-            MatcherError::VectorizationError(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.clone()),
-            MatcherError::QdrantSearchError(e) => (StatusCode::BAD_GATEWAY, e.clone()),
-            MatcherError::InternalError(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.clone()),
-        };
-
         let body = Json(json!({
-            "error": error_message,
+            "error": self.message(),
         }));
 
-        (status, body).into_response()
+        (self.status(), body).into_response()
     }
 }
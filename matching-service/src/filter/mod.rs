@@ -0,0 +1,148 @@
+use crate::error::MatcherError;
+use chrono::DateTime;
+use qdrant_client::qdrant::{Condition, DatetimeRange, Filter, Range, Timestamp};
+use serde_json::Value;
+use shared_types::{FilterCondition, MatchFilter};
+
+/// Translate a request filter into a Qdrant filter
+///
+/// Returns `MatcherError::InvalidFilter` describing the first offending
+/// condition so the caller gets a 400 instead of an opaque Qdrant error.
+pub fn to_qdrant_filter(filter: &MatchFilter) -> Result<Filter, MatcherError> {
+    Ok(Filter {
+        must: to_conditions(&filter.must)?,
+        should: to_conditions(&filter.should)?,
+        must_not: to_conditions(&filter.must_not)?,
+        ..Default::default()
+    })
+}
+
+fn to_conditions(conditions: &[FilterCondition]) -> Result<Vec<Condition>, MatcherError> {
+    conditions.iter().map(to_condition).collect()
+}
+
+fn to_condition(condition: &FilterCondition) -> Result<Condition, MatcherError> {
+    match condition {
+        FilterCondition::Equals { key, value } => {
+            let key = validate_key(key)?;
+            match value {
+                Value::String(s) => Ok(Condition::matches(key, s.clone())),
+                Value::Bool(b) => Ok(Condition::matches(key, *b)),
+                Value::Number(n) => {
+                    n.as_i64()
+                        .map(|i| Condition::matches(key, i))
+                        .ok_or_else(|| {
+                            invalid(format!("equals on '{key}' only supports integer numbers"))
+                        })
+                }
+                _ => Err(invalid(format!(
+                    "equals on '{key}' expects a string, integer or boolean value"
+                ))),
+            }
+        }
+        FilterCondition::AnyOf { key, values } => {
+            let key = validate_key(key)?;
+            if values.is_empty() {
+                return Err(invalid(format!(
+                    "any_of on '{key}' requires at least one value"
+                )));
+            }
+            if let Some(keywords) = values
+                .iter()
+                .map(|v| v.as_str().map(str::to_string))
+                .collect::<Option<Vec<String>>>()
+            {
+                Ok(Condition::matches(key, keywords))
+            } else if let Some(integers) = values
+                .iter()
+                .map(Value::as_i64)
+                .collect::<Option<Vec<i64>>>()
+            {
+                Ok(Condition::matches(key, integers))
+            } else {
+                Err(invalid(format!(
+                    "any_of on '{key}' expects all strings or all integers"
+                )))
+            }
+        }
+        FilterCondition::Range {
+            key,
+            gt,
+            gte,
+            lt,
+            lte,
+        } => {
+            let key = validate_key(key)?;
+            let bounds = [gt, gte, lt, lte];
+            if bounds.iter().all(|b| b.is_none()) {
+                return Err(invalid(format!(
+                    "range on '{key}' requires at least one bound"
+                )));
+            }
+            if bounds.iter().flat_map(|b| b.iter()).any(|b| !b.is_finite()) {
+                return Err(invalid(format!("range on '{key}' has a non-finite bound")));
+            }
+            Ok(Condition::range(
+                key,
+                Range {
+                    gt: *gt,
+                    gte: *gte,
+                    lt: *lt,
+                    lte: *lte,
+                },
+            ))
+        }
+        FilterCondition::DatetimeRange {
+            key,
+            gt,
+            gte,
+            lt,
+            lte,
+        } => {
+            let key = validate_key(key)?;
+            if [gt, gte, lt, lte].iter().all(|b| b.is_none()) {
+                return Err(invalid(format!(
+                    "datetime_range on '{key}' requires at least one bound"
+                )));
+            }
+            Ok(Condition::datetime_range(
+                key,
+                DatetimeRange {
+                    gt: parse_timestamp(key, gt.as_deref())?,
+                    gte: parse_timestamp(key, gte.as_deref())?,
+                    lt: parse_timestamp(key, lt.as_deref())?,
+                    lte: parse_timestamp(key, lte.as_deref())?,
+                },
+            ))
+        }
+        FilterCondition::Filter(nested) => Ok(Condition::from(to_qdrant_filter(nested)?)),
+    }
+}
+
+fn validate_key(key: &str) -> Result<&str, MatcherError> {
+    if key.trim().is_empty() {
+        return Err(invalid("filter key must not be empty".to_string()));
+    }
+    Ok(key)
+}
+
+fn parse_timestamp(key: &str, value: Option<&str>) -> Result<Option<Timestamp>, MatcherError> {
+    value
+        .map(|raw| {
+            DateTime::parse_from_rfc3339(raw)
+                .map(|dt| Timestamp {
+                    seconds: dt.timestamp(),
+                    nanos: dt.timestamp_subsec_nanos() as i32,
+                })
+                .map_err(|e| {
+                    invalid(format!(
+                        "datetime_range on '{key}' has invalid RFC 3339 bound '{raw}': {e}"
+                    ))
+                })
+        })
+        .transpose()
+}
+
+fn invalid(message: String) -> MatcherError {
+    MatcherError::InvalidFilter(message)
+}
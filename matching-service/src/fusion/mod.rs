@@ -0,0 +1,261 @@
+use crate::error::MatcherError;
+use shared_types::{MatchResult, Retrieval};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use uuid::Uuid;
+
+/// Reject fusion settings that cannot produce a ranking
+pub fn validate(retrieval: &Retrieval) -> Result<(), MatcherError> {
+    match retrieval {
+        Retrieval::Weighted {
+            dense_weight,
+            sparse_weight,
+        } if !(dense_weight.is_finite() && sparse_weight.is_finite())
+            || *dense_weight < 0.0
+            || *sparse_weight < 0.0
+            || dense_weight + sparse_weight <= 0.0 =>
+        {
+            Err(MatcherError::InvalidQuery(
+                "weighted retrieval requires non-negative weights with a positive sum".to_string(),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether the mode runs the dense (semantic) search
+pub fn uses_dense(retrieval: &Retrieval) -> bool {
+    !matches!(retrieval, Retrieval::Sparse)
+}
+
+/// Whether the mode runs the sparse (keyword) search
+pub fn uses_sparse(retrieval: &Retrieval) -> bool {
+    !matches!(retrieval, Retrieval::Dense)
+}
+
+/// Whether the mode combines two rankings, which then need deeper candidate lists
+pub fn is_fused(retrieval: &Retrieval) -> bool {
+    uses_dense(retrieval) && uses_sparse(retrieval)
+}
+
+/// Combine the document rankings of both searches into the final top `top_k`
+///
+/// Each list must be sorted best first. A document found by only one
+/// search contributes nothing from the other. Its chunks are taken from
+/// the dense search when it has them, as they point at the semantically
+/// closest spans.
+pub fn fuse(
+    dense: Vec<MatchResult>,
+    sparse: Vec<MatchResult>,
+    retrieval: &Retrieval,
+    top_k: u64,
+) -> Vec<MatchResult> {
+    let mut results = match *retrieval {
+        Retrieval::Dense => dense,
+        Retrieval::Sparse => sparse,
+        Retrieval::Rrf { k } => {
+            let dense_scores = reciprocal_ranks(&dense, k);
+            let sparse_scores = reciprocal_ranks(&sparse, k);
+            combine([(dense, dense_scores), (sparse, sparse_scores)])
+        }
+        Retrieval::Weighted {
+            dense_weight,
+            sparse_weight,
+        } => {
+            // Dense and sparse scores live on different scales
+            let dense_scores = normalized(&dense, dense_weight);
+            let sparse_scores = normalized(&sparse, sparse_weight);
+            combine([(dense, dense_scores), (sparse, sparse_scores)])
+        }
+    };
+
+    results.truncate(usize::try_from(top_k).unwrap_or(usize::MAX));
+    results
+}
+
+/// Sum the contributions of each document over both lists and rank by the total
+///
+/// The dense list comes first, so its chunks win for documents in both.
+fn combine(lists: [(Vec<MatchResult>, Vec<f32>); 2]) -> Vec<MatchResult> {
+    let mut fused: HashMap<Uuid, MatchResult> = HashMap::new();
+
+    for (list, scores) in lists {
+        for (result, score) in list.into_iter().zip(scores) {
+            match fused.entry(result.id) {
+                Entry::Occupied(mut entry) => {
+                    let existing = entry.get_mut();
+                    existing.score += score;
+                    if existing.chunks.is_empty() {
+                        existing.chunks = result.chunks;
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(MatchResult { score, ..result });
+                }
+            }
+        }
+    }
+
+    // Ties are broken by id so equal scores rank the same on every request
+    let mut results: Vec<MatchResult> = fused.into_values().collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+    results
+}
+
+/// Reciprocal rank contribution of every document of a ranking
+fn reciprocal_ranks(list: &[MatchResult], k: u32) -> Vec<f32> {
+    (1..=list.len())
+        .map(|rank| 1.0 / (k as f32 + rank as f32))
+        .collect()
+}
+
+/// Min-max normalise the scores of a ranking into [0, 1], then apply `weight`
+///
+/// A list whose scores are all equal maps to `weight` throughout.
+fn normalized(list: &[MatchResult], weight: f32) -> Vec<f32> {
+    let min = list.iter().map(|r| r.score).fold(f32::INFINITY, f32::min);
+    let max = list
+        .iter()
+        .map(|r| r.score)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+
+    list.iter()
+        .map(|r| {
+            let score = if range > 0.0 {
+                (r.score - min) / range
+            } else {
+                1.0
+            };
+            weight * score
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_types::MatchedChunk;
+
+    fn result(id: u128, score: f32) -> MatchResult {
+        MatchResult {
+            id: Uuid::from_u128(id),
+            score,
+            retrieval_score: None,
+            rerank_score: None,
+            chunks: Vec::new(),
+        }
+    }
+
+    fn with_chunk(mut result: MatchResult, index: usize) -> MatchResult {
+        result.chunks.push(MatchedChunk {
+            index,
+            start: 0,
+            end: 0,
+            score: result.score,
+            text: None,
+        });
+        result
+    }
+
+    fn ids(results: &[MatchResult]) -> Vec<u128> {
+        results.iter().map(|r| r.id.as_u128()).collect()
+    }
+
+    fn weighted(dense_weight: f32, sparse_weight: f32) -> Retrieval {
+        Retrieval::Weighted {
+            dense_weight,
+            sparse_weight,
+        }
+    }
+
+    #[test]
+    fn validate_rejects_unusable_weights() {
+        assert!(validate(&weighted(-1.0, 1.0)).is_err());
+        assert!(validate(&weighted(0.0, 0.0)).is_err());
+        assert!(validate(&weighted(f32::NAN, 1.0)).is_err());
+        assert!(validate(&weighted(f32::INFINITY, 1.0)).is_err());
+        assert!(validate(&weighted(0.0, 1.0)).is_ok());
+        assert!(validate(&Retrieval::Rrf { k: 60 }).is_ok());
+    }
+
+    #[test]
+    fn modes_select_their_searches() {
+        assert!(uses_dense(&Retrieval::Dense) && !uses_sparse(&Retrieval::Dense));
+        assert!(!uses_dense(&Retrieval::Sparse) && uses_sparse(&Retrieval::Sparse));
+        assert!(is_fused(&Retrieval::Rrf { k: 60 }));
+        assert!(is_fused(&weighted(1.0, 1.0)));
+        assert!(!is_fused(&Retrieval::Dense));
+    }
+
+    #[test]
+    fn single_searches_pass_through_truncated() {
+        let dense = vec![result(1, 0.9), result(2, 0.8), result(3, 0.7)];
+
+        assert_eq!(
+            ids(&fuse(dense, vec![result(4, 9.0)], &Retrieval::Dense, 2)),
+            vec![1, 2]
+        );
+        assert_eq!(
+            ids(&fuse(
+                Vec::new(),
+                vec![result(4, 9.0)],
+                &Retrieval::Sparse,
+                10
+            )),
+            vec![4]
+        );
+    }
+
+    #[test]
+    fn rrf_rewards_documents_found_by_both_searches() {
+        let dense = vec![result(1, 0.9), result(2, 0.8)];
+        let sparse = vec![result(2, 12.0), result(3, 7.0)];
+        let fused = fuse(dense, sparse, &Retrieval::Rrf { k: 60 }, 10);
+
+        assert_eq!(ids(&fused), vec![2, 1, 3]);
+        assert!((fused[0].score - (1.0 / 62.0 + 1.0 / 61.0)).abs() < 1e-6);
+        assert!((fused[1].score - 1.0 / 61.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn weighted_fusion_normalises_each_ranking() {
+        let dense = vec![result(1, 0.9), result(2, 0.5)];
+        let sparse = vec![result(2, 10.0), result(3, 4.0)];
+        let fused = fuse(dense, sparse, &weighted(0.5, 0.5), 10);
+
+        // Documents 1 and 2 tie at 0.5 and are ordered by id
+        assert_eq!(ids(&fused), vec![1, 2, 3]);
+        assert_eq!(
+            fused.iter().map(|r| r.score).collect::<Vec<_>>(),
+            vec![0.5, 0.5, 0.0]
+        );
+    }
+
+    #[test]
+    fn equal_scores_normalise_to_the_weight() {
+        let dense = vec![result(1, 0.7), result(2, 0.7)];
+        let fused = fuse(dense, Vec::new(), &weighted(0.3, 0.7), 10);
+
+        assert!(fused.iter().all(|r| (r.score - 0.3).abs() < 1e-6));
+    }
+
+    #[test]
+    fn fused_results_keep_the_dense_chunks() {
+        let dense = vec![with_chunk(result(1, 0.9), 4)];
+        let sparse = vec![with_chunk(result(1, 3.0), 7), with_chunk(result(2, 2.0), 1)];
+        let fused = fuse(dense, sparse, &Retrieval::Rrf { k: 60 }, 10);
+
+        assert_eq!(fused[0].chunks[0].index, 4);
+        assert_eq!(fused[1].chunks[0].index, 1);
+    }
+
+    #[test]
+    fn sparse_chunks_fill_in_when_dense_has_none() {
+        let dense = vec![result(1, 0.9)];
+        let sparse = vec![with_chunk(result(1, 3.0), 7)];
+        let fused = fuse(dense, sparse, &Retrieval::Rrf { k: 60 }, 10);
+
+        assert_eq!(fused[0].chunks[0].index, 7);
+    }
+}
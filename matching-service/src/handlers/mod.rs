@@ -1,10 +1,20 @@
-use crate::{error::MatcherError, qdrant, state::AppState};
+use crate::{
+    aggregation, batch,
+    error::MatcherError,
+    filter, fusion, paging,
+    qdrant::{self, GroupSearch, QueryVector},
+    rerank::{self, Reranker},
+    similar,
+    state::AppState,
+};
 use axum::{Json, extract::State, http::StatusCode};
-use qdrant_client::qdrant::point_id::PointIdOptions;
-use shared_types::{MatchQuery, MatchResponse, MatchResult};
-use std::str::FromStr;
+use futures::StreamExt;
+use shared_embedder::bm25;
+use shared_types::{
+    BatchMatchResponse, BatchMatchResult, DenseVector, MatchQuery, MatchResponse, MatchResult,
+    Retrieval, ScoreAggregation, SimilarQuery,
+};
 use std::sync::Arc;
-use uuid::Uuid;
 
 /// Health check endpoint
 pub async fn health_check() -> StatusCode {
@@ -13,60 +23,427 @@ pub async fn health_check() -> StatusCode {
 
 /// Find matches handler
 ///
-/// 1. Vectorizes the query text using the BERT model (blocking CPU task)
-/// 2. Searches Qdrant for the nearest neighbours (async I/O)
-/// 3. Maps scored points to the API response format
+/// 1. Validates the optional payload filter, aggregation, retrieval mode,
+///    rerank choice, page size, offset and score threshold (rejected with 400
+///    before any inference)
+/// 2. Runs the dense search, the sparse keyword search, or both concurrently;
+///    each searches Qdrant for the nearest chunks grouped by document and
+///    aggregates chunk scores into ranked document results, and counts the
+///    documents above the score threshold when there is one
+/// 3. Fuses the two rankings when both ran
+/// 4. Rescores the candidates with the cross-encoder when reranking
+/// 5. Returns the requested page of the ranking
 pub async fn find_matches(
     State(state): State<Arc<AppState>>,
     Json(query): Json<MatchQuery>,
 ) -> Result<Json<MatchResponse>, MatcherError> {
     tracing::info!("Received match query, length: {}", query.content.len());
 
-    // 1. Vectorize the Query Text (blocking CPU task)
-    let state_ref = state.clone();
-    let query_text = query.content.clone();
+    let plan = plan(&state, &query)?;
+    let response = run(&state, &query, plan, None).await?;
 
-    let vector_result = tokio::task::spawn_blocking(move || {
-        let model_guard = state_ref.model.lock().expect("Mutex poisoned");
-        model_guard.encode(&[query_text])
-    })
-    .await
-    .map_err(|e| MatcherError::InternalError(e.to_string()))?;
+    tracing::info!("Found {} matches", response.matches.len());
 
-    let query_vector = vector_result
-        .map_err(|e| MatcherError::VectorizationError(e.to_string()))?
-        .into_iter()
-        .next()
-        .ok_or_else(|| MatcherError::VectorizationError("No vector generated".into()))?;
+    Ok(Json(response))
+}
 
-    // 2. Search Qdrant (async I/O task)
-    tracing::info!("Executing ANN search in Qdrant...");
-    let scored_points = qdrant::search_nearest(
-        &state.qdrant,
-        &state.config.collection_name,
-        query_vector,
-        query.top_k,
-    )
-    .await?;
+/// Batch match handler
+///
+/// 1. Parses and validates every query on its own; a malformed or invalid
+///    query fails alone
+/// 2. Embeds the content of every query using dense retrieval in a single
+///    embedder call
+/// 3. Runs the queries concurrently, at most `batch.max_in_flight` at a time,
+///    each exactly as `/match` would
+/// 4. Returns one result per query, in request order, carrying either the
+///    `MatchResponse` or the status and error the query would have received
+pub async fn find_matches_batch(
+    State(state): State<Arc<AppState>>,
+    Json(batch): Json<batch::BatchMatchQuery>,
+) -> Result<Json<BatchMatchResponse>, MatcherError> {
+    tracing::info!(queries = batch.queries.len(), "Received batch match query");
+    batch::validate(&batch, &state.config.batch)?;
 
-    // 3. Map results to API response format
-    let results: Vec<MatchResult> = scored_points
+    // 1. Parse and validate each query
+    let planned: Vec<Result<_, MatcherError>> = batch
+        .queries
         .into_iter()
-        .filter_map(|scored_point| {
-            if let Some(PointIdOptions::Uuid(uuid_str)) =
-                scored_point.id.and_then(|id| id.point_id_options)
-            {
-                Uuid::from_str(&uuid_str).ok().map(|uuid| MatchResult {
-                    id: uuid,
-                    score: scored_point.score,
-                })
+        .map(|entry| {
+            let query = batch::parse(entry)?;
+            let plan = plan(&state, &query)?;
+            Ok((query, plan))
+        })
+        .collect();
+
+    // 2. Embed the dense queries together; if that fails, each of them fails
+    let texts: Vec<String> = planned
+        .iter()
+        .filter_map(|entry| match entry {
+            Ok((query, plan)) if fusion::uses_dense(&plan.retrieval) => Some(query.content.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut vectors = if texts.is_empty() {
+        Ok(Vec::new().into_iter())
+    } else {
+        tracing::info!(texts = texts.len(), "Embedding batch queries...");
+        shared_embedder::embed(&state.embedder, texts)
+            .await
+            .map(Vec::into_iter)
+            .map_err(MatcherError::from)
+    };
+    let planned: Vec<_> = planned
+        .into_iter()
+        .map(|entry| {
+            let (query, plan) = entry?;
+            let query_vector = if fusion::uses_dense(&plan.retrieval) {
+                let vector = match &mut vectors {
+                    Ok(vectors) => vectors.next().ok_or_else(|| {
+                        MatcherError::VectorizationError("No vector generated".into())
+                    })?,
+                    Err(e) => return Err(e.clone()),
+                };
+                Some(vector)
             } else {
                 None
-            }
+            };
+            Ok((query, plan, query_vector))
         })
         .collect();
 
-    tracing::info!("Found {} matches", results.len());
+    // 3. Search; Qdrant's batch APIs only take ungrouped searches, so the
+    //    grouped searches run concurrently instead
+    let searches: Vec<_> = planned
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| run_batch_entry(&state, index, entry))
+        .collect();
+    let results = futures::stream::iter(searches)
+        .buffered(state.config.batch.max_in_flight.max(1))
+        .collect()
+        .await;
+
+    // 4. Report every query in order
+    let response = batch::summarize(results);
+
+    tracing::info!(
+        succeeded = response.succeeded,
+        failed = response.failed,
+        "Processed batch match query"
+    );
+
+    Ok(Json(response))
+}
+
+/// More-like-this handler
+///
+/// 1. Validates the seed documents, payload filter and aggregation
+///    (rejected with 400 before touching Qdrant)
+/// 2. Looks up the indexed chunks of the seeds; fails with 404 if a seed
+///    has none
+/// 3. Asks Qdrant for chunks like the positive seeds' stored vectors and
+///    unlike the negative ones, grouped by document and never from a seed,
+///    and aggregates chunk scores into ranked document results
+pub async fn find_similar(
+    State(state): State<Arc<AppState>>,
+    Json(query): Json<SimilarQuery>,
+) -> Result<Json<MatchResponse>, MatcherError> {
+    tracing::info!(
+        positive = query.positive.len(),
+        negative = query.negative.len(),
+        "Received similarity query"
+    );
 
-    Ok(Json(MatchResponse { matches: results }))
+    // 1. Validate the request
+    similar::validate(&query, &state.config.similar)?;
+    paging::validate_top_k(query.top_k, &state.config.paging)?;
+    let search_filter = query
+        .filter
+        .as_ref()
+        .map(filter::to_qdrant_filter)
+        .transpose()?;
+    let strategy = query.aggregation.unwrap_or(state.config.aggregation);
+    aggregation::validate(&strategy)?;
+
+    // 2. Resolve the seed documents to their chunk points
+    let seeds = similar::seeds(&query);
+    let points =
+        qdrant::document_points(&state.qdrant, &state.config.collection_name, &seeds).await?;
+    let (positive, negative) = similar::example_points(&query, points)?;
+
+    // 3. Recommend
+    tracing::info!("Executing grouped recommendation in Qdrant...");
+    let groups = qdrant::recommend_groups(
+        &state.qdrant,
+        &state.config.collection_name,
+        positive,
+        negative,
+        GroupSearch {
+            limit: query.top_k,
+            group_size: aggregation::group_size(&strategy, state.config.chunks_per_document),
+            filter: Some(similar::exclude_seeds(search_filter, &seeds)),
+            score_threshold: None,
+            with_text: false,
+        },
+    )
+    .await?;
+    let matches = aggregation::to_match_results(groups, &strategy);
+
+    tracing::info!("Found {} similar documents", matches.len());
+
+    Ok(Json(MatchResponse {
+        matches,
+        next_offset: None,
+        total_above_threshold: None,
+    }))
+}
+
+/// A validated match query and how deep to search for it
+struct MatchPlan<'a> {
+    strategy: ScoreAggregation,
+    retrieval: Retrieval,
+    reranker: Option<&'a Reranker>,
+    offset: u64,
+    /// Documents up to the end of the page, and one more
+    window: u64,
+    /// Documents ranked before reranking and paging
+    depth: u64,
+    search: GroupSearch,
+}
+
+/// Validate a match query and work out its searches
+///
+/// Fails with 400 on invalid input, before any inference.
+fn plan<'a>(state: &'a AppState, query: &MatchQuery) -> Result<MatchPlan<'a>, MatcherError> {
+    // 1. Translate the payload filter and check the query's options
+    let search_filter = query
+        .filter
+        .as_ref()
+        .map(filter::to_qdrant_filter)
+        .transpose()?;
+    let strategy = query.aggregation.unwrap_or(state.config.aggregation);
+    aggregation::validate(&strategy)?;
+    let retrieval = query.retrieval.unwrap_or(state.config.retrieval);
+    fusion::validate(&retrieval)?;
+    let reranker = rerank::select(query.rerank, state.reranker.as_ref())?;
+    let offset = query.offset.unwrap_or(0);
+    paging::validate(
+        query.top_k,
+        offset,
+        query.score_threshold,
+        &strategy,
+        &retrieval,
+        &state.config.paging,
+    )?;
+
+    // Search everything up to the end of the page (and one more, to know
+    // whether another page follows). Reranked pages are all cut from the
+    // same candidates, so the depth does not change from page to page.
+    // Fused searches go deeper still.
+    let (window, depth) = match reranker {
+        Some(reranker) => {
+            paging::validate_reranked(offset, query.top_k, reranker.candidates)?;
+            let window = paging::window(offset, query.top_k).min(reranker.candidates);
+            (window, reranker.candidates)
+        }
+        None => {
+            let window = paging::window(offset, query.top_k);
+            (window, window)
+        }
+    };
+    let search = GroupSearch {
+        limit: if fusion::is_fused(&retrieval) {
+            depth.saturating_mul(state.config.hybrid.candidate_multiplier.max(1))
+        } else {
+            depth
+        },
+        group_size: aggregation::group_size(&strategy, state.config.chunks_per_document),
+        filter: search_filter,
+        score_threshold: query.score_threshold,
+        with_text: reranker.is_some(),
+    };
+
+    Ok(MatchPlan {
+        strategy,
+        retrieval,
+        reranker,
+        offset,
+        window,
+        depth,
+        search,
+    })
+}
+
+/// Run one query of a batch, unless it already failed
+async fn run_batch_entry(
+    state: &AppState,
+    index: usize,
+    entry: Result<(MatchQuery, MatchPlan<'_>, Option<DenseVector>), MatcherError>,
+) -> BatchMatchResult {
+    let outcome = match entry {
+        Ok((query, plan, query_vector)) => run(state, &query, plan, query_vector).await,
+        Err(e) => Err(e),
+    };
+    batch::result(index, outcome)
+}
+
+/// Search, fuse, rerank and page a planned query
+///
+/// The dense search embeds the query content unless its vector is given.
+async fn run(
+    state: &AppState,
+    query: &MatchQuery,
+    plan: MatchPlan<'_>,
+    query_vector: Option<DenseVector>,
+) -> Result<MatchResponse, MatcherError> {
+    // 2. Run the searches
+    let dense = async {
+        if fusion::uses_dense(&plan.retrieval) {
+            dense_search(
+                state,
+                &query.content,
+                query_vector,
+                plan.search.clone(),
+                &plan.strategy,
+            )
+            .await
+        } else {
+            Ok(Ranking::default())
+        }
+    };
+    let sparse = async {
+        if fusion::uses_sparse(&plan.retrieval) {
+            sparse_search(state, &query.content, plan.search.clone(), &plan.strategy).await
+        } else {
+            Ok(Ranking::default())
+        }
+    };
+    let (dense, sparse) = tokio::try_join!(dense, sparse)?;
+    // A threshold is only accepted when a single search runs
+    let total_above_threshold = dense.total_above_threshold.or(sparse.total_above_threshold);
+
+    // 3. Fuse the rankings
+    let results = fusion::fuse(dense.results, sparse.results, &plan.retrieval, plan.depth);
+
+    // 4. Rerank
+    let results = match plan.reranker {
+        Some(reranker) => {
+            tracing::info!(
+                candidates = results.len(),
+                "Reranking with the cross-encoder..."
+            );
+            reranker
+                .rerank(&query.content, results, plan.window)
+                .await?
+        }
+        None => results,
+    };
+
+    // 5. Cut out the page
+    let (matches, next_offset) = paging::page(results, plan.offset, query.top_k);
+
+    Ok(MatchResponse {
+        matches,
+        next_offset,
+        total_above_threshold,
+    })
+}
+
+/// Documents ranked by one search
+#[derive(Default)]
+struct Ranking {
+    results: Vec<MatchResult>,
+    /// Documents above the score threshold, when the query has one
+    total_above_threshold: Option<u64>,
+}
+
+/// Semantic search: embed the query and rank documents by their nearest chunks
+///
+/// A batch passes the query vector it already embedded.
+async fn dense_search(
+    state: &AppState,
+    content: &str,
+    query_vector: Option<DenseVector>,
+    search: GroupSearch,
+    strategy: &ScoreAggregation,
+) -> Result<Ranking, MatcherError> {
+    // Vectorize the query text (blocking CPU task)
+    let query_vector = match query_vector {
+        Some(query_vector) => query_vector,
+        None => shared_embedder::embed(&state.embedder, vec![content.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| MatcherError::VectorizationError("No vector generated".into()))?,
+    };
+
+    tracing::info!("Executing grouped ANN search in Qdrant...");
+    rank(state, QueryVector::Dense(query_vector), search, strategy).await
+}
+
+/// Keyword search: rank documents by the BM25 scores of their chunks
+///
+/// Qdrant applies the inverse document frequency of each query term, so the
+/// query itself carries plain term weights.
+async fn sparse_search(
+    state: &AppState,
+    content: &str,
+    search: GroupSearch,
+    strategy: &ScoreAggregation,
+) -> Result<Ranking, MatcherError> {
+    let query_vector = bm25::encode_query(content);
+    if query_vector.is_empty() {
+        return Ok(Ranking {
+            results: Vec::new(),
+            total_above_threshold: search.score_threshold.map(|_| 0),
+        });
+    }
+
+    tracing::info!("Executing grouped keyword search in Qdrant...");
+    let query_vector = QueryVector::Sparse {
+        name: state.config.hybrid.sparse_vector_name.clone(),
+        vector: query_vector,
+    };
+    rank(state, query_vector, search, strategy).await
+}
+
+/// Run the grouped search, and the threshold count alongside when needed
+async fn rank(
+    state: &AppState,
+    query_vector: QueryVector,
+    search: GroupSearch,
+    strategy: &ScoreAggregation,
+) -> Result<Ranking, MatcherError> {
+    let count = {
+        let query_vector = query_vector.clone();
+        let filter = search.filter.clone();
+        let score_threshold = search.score_threshold;
+        async move {
+            match score_threshold {
+                Some(score_threshold) => qdrant::count_groups(
+                    &state.qdrant,
+                    &state.config.collection_name,
+                    query_vector,
+                    filter,
+                    score_threshold,
+                    state.config.paging.count_limit,
+                )
+                .await
+                .map(Some),
+                None => Ok(None),
+            }
+        }
+    };
+    let groups = qdrant::search_groups(
+        &state.qdrant,
+        &state.config.collection_name,
+        query_vector,
+        search,
+    );
+    let (groups, total_above_threshold) = tokio::try_join!(groups, count)?;
+
+    Ok(Ranking {
+        results: aggregation::to_match_results(groups, strategy),
+        total_above_threshold,
+    })
 }
@@ -1,17 +1,24 @@
+mod aggregation;
+mod batch;
 mod config;
 mod error;
+mod filter;
+mod fusion;
 mod handlers;
+mod paging;
 mod qdrant;
+mod rerank;
 mod routes;
+mod similar;
 mod state;
 
 use config::Config;
-use rust_bert::pipelines::sentence_embeddings::{
-    SentenceEmbeddingsBuilder, SentenceEmbeddingsModelType,
-};
+use rerank::Reranker;
+use shared_runtime::Shutdown;
 use state::AppState;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -29,30 +36,55 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let server_host: IpAddr = config.server_host.parse()?;
     let server_port = config.server_port;
+    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout_secs);
 
     // 1. Load ML Model (blocking operation at startup)
-    tracing::info!("Loading BERT model (this may take a moment)...");
-    let model = tokio::task::spawn_blocking(|| {
-        SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL6V2)
-            .create_model()
-            .expect("Failed to load ML model")
-    })
-    .await?;
-    tracing::info!("BERT model loaded.");
+    tracing::info!("Loading embedding model (this may take a moment)...");
+    let embedder = shared_embedder::load(&config.embedder).await?;
+    tracing::info!("Embedding model loaded.");
+
+    // Optional cross-encoder for the reranking stage, loaded the same way
+    let reranker = match &config.rerank {
+        Some(rerank) => Some(Reranker {
+            cross_encoder: shared_embedder::load_cross_encoder(&rerank.cross_encoder).await?,
+            candidates: rerank.candidates,
+        }),
+        None => None,
+    };
 
     // 2. Initialize Qdrant client
     let qdrant_client = qdrant::create_client(&config).await?;
     tracing::info!("Qdrant client initialized.");
 
+    // Query vectors must have the same dimension as the indexed documents
+    match qdrant::collection_vector_size(&qdrant_client, &config.collection_name).await? {
+        Some(size) => shared_embedder::verify_dimension(embedder.as_ref(), size as usize)?,
+        None => tracing::warn!("Collection uses named vectors, skipping dimension check"),
+    }
+
     // 3. Build shared state
-    let state = Arc::new(AppState::new(model, qdrant_client, config));
+    let state = Arc::new(AppState::new(embedder, qdrant_client, reranker, config));
 
     // 4. Build router and start server
     let app = routes::create_router(state);
     let addr = SocketAddr::from((server_host, server_port));
     tracing::info!("Matching Service listening on {}", addr);
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+
+    // Stop accepting connections on SIGTERM/SIGINT and let in-flight queries
+    // finish, but never for longer than the configured deadline
+    let shutdown = Shutdown::listen();
+    let signal = shutdown.clone();
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        signal.triggered().await;
+    });
+    tokio::select! {
+        result = server => result?,
+        _ = shutdown.deadline(shutdown_timeout) => {
+            tracing::warn!("Shutdown deadline exceeded, dropping open connections");
+        }
+    }
+    tracing::info!("Matching Service stopped");
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,315 @@
+use crate::config::PagingConfig;
+use crate::error::MatcherError;
+use crate::fusion;
+use shared_types::{MatchResult, Retrieval, ScoreAggregation};
+
+/// Reject a page size above `max_top_k`
+pub fn validate_top_k(top_k: u64, config: &PagingConfig) -> Result<(), MatcherError> {
+    if top_k > config.max_top_k {
+        return Err(MatcherError::InvalidQuery(format!(
+            "top_k must be at most {}, got {}",
+            config.max_top_k, top_k
+        )));
+    }
+    Ok(())
+}
+
+/// Reject paging and threshold settings the service cannot honour
+pub fn validate(
+    top_k: u64,
+    offset: u64,
+    score_threshold: Option<f32>,
+    strategy: &ScoreAggregation,
+    retrieval: &Retrieval,
+    config: &PagingConfig,
+) -> Result<(), MatcherError> {
+    validate_top_k(top_k, config)?;
+    if offset > config.max_offset {
+        return Err(MatcherError::InvalidQuery(format!(
+            "offset must be at most {}, got {}",
+            config.max_offset, offset
+        )));
+    }
+
+    match score_threshold {
+        Some(threshold) if !threshold.is_finite() => Err(MatcherError::InvalidQuery(
+            "score_threshold must be a finite number".to_string(),
+        )),
+        // Dense and sparse scores live on different scales, so one
+        // threshold cannot apply to both searches
+        Some(_) if fusion::is_fused(retrieval) => Err(MatcherError::InvalidQuery(
+            "score_threshold cannot be combined with rrf or weighted retrieval".to_string(),
+        )),
+        // The threshold applies to chunks: it equals a bound on the document
+        // score, and a count of the documents above it, only when the score
+        // is the best chunk's
+        Some(_) if !matches!(strategy, ScoreAggregation::Max) => Err(MatcherError::InvalidQuery(
+            "score_threshold requires max aggregation".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Documents to rank so the page and one more result are known
+///
+/// Qdrant's grouped search and recommend take no offset, so the service
+/// ranks every document before the page and drops them itself. The extra
+/// result tells whether a next page exists.
+pub fn window(offset: u64, top_k: u64) -> u64 {
+    offset.saturating_add(top_k).saturating_add(1)
+}
+
+/// Reject a page reaching past the `candidates` reranked documents
+///
+/// Every reranked page is cut from the same candidates, ranked to the same
+/// depth, so consecutive pages never overlap or skip a document. There is
+/// nothing reranked beyond them to page through.
+pub fn validate_reranked(offset: u64, top_k: u64, candidates: u64) -> Result<(), MatcherError> {
+    if offset.saturating_add(top_k) > candidates {
+        return Err(MatcherError::InvalidQuery(format!(
+            "with reranking, offset + top_k must be at most rerank.candidates ({}), got {}",
+            candidates,
+            offset.saturating_add(top_k)
+        )));
+    }
+    Ok(())
+}
+
+/// Cut the page out of the ranked results
+///
+/// Returns the page and the offset of the next one, if any results follow.
+pub fn page(results: Vec<MatchResult>, offset: u64, top_k: u64) -> (Vec<MatchResult>, Option<u64>) {
+    let start = usize::try_from(offset).unwrap_or(usize::MAX);
+    let size = usize::try_from(top_k).unwrap_or(usize::MAX);
+    let has_more = size > 0 && results.len() > start.saturating_add(size);
+
+    let page = results.into_iter().skip(start).take(size).collect();
+    (page, has_more.then(|| offset.saturating_add(top_k)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn results(count: u128) -> Vec<MatchResult> {
+        (0..count)
+            .map(|id| MatchResult {
+                id: Uuid::from_u128(id),
+                score: 1.0 - id as f32 / 100.0,
+                retrieval_score: None,
+                rerank_score: None,
+                chunks: Vec::new(),
+            })
+            .collect()
+    }
+
+    fn ids(results: &[MatchResult]) -> Vec<u128> {
+        results.iter().map(|r| r.id.as_u128()).collect()
+    }
+
+    #[test]
+    fn validate_bounds_the_offset() {
+        let config = PagingConfig::default();
+
+        assert!(
+            validate(
+                10,
+                config.max_offset,
+                None,
+                &ScoreAggregation::Max,
+                &Retrieval::Dense,
+                &config
+            )
+            .is_ok()
+        );
+        assert!(
+            validate(
+                10,
+                config.max_offset + 1,
+                None,
+                &ScoreAggregation::Max,
+                &Retrieval::Dense,
+                &config
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn validate_bounds_top_k() {
+        let config = PagingConfig::default();
+
+        assert!(
+            validate(
+                config.max_top_k,
+                0,
+                None,
+                &ScoreAggregation::Max,
+                &Retrieval::Dense,
+                &config
+            )
+            .is_ok()
+        );
+        assert!(
+            validate(
+                config.max_top_k + 1,
+                0,
+                None,
+                &ScoreAggregation::Max,
+                &Retrieval::Dense,
+                &config
+            )
+            .is_err()
+        );
+        assert!(validate_top_k(u64::MAX, &config).is_err());
+    }
+
+    #[test]
+    fn reranked_pages_stay_within_the_candidates() {
+        assert!(validate_reranked(0, 20, 20).is_ok());
+        assert!(validate_reranked(10, 10, 20).is_ok());
+        assert!(validate_reranked(20, 10, 20).is_err());
+        assert!(validate_reranked(0, 21, 20).is_err());
+        assert!(validate_reranked(u64::MAX, 10, 20).is_err());
+    }
+
+    #[test]
+    fn validate_checks_the_threshold() {
+        let config = PagingConfig::default();
+
+        assert!(
+            validate(
+                10,
+                0,
+                Some(0.5),
+                &ScoreAggregation::Max,
+                &Retrieval::Dense,
+                &config
+            )
+            .is_ok()
+        );
+        assert!(
+            validate(
+                10,
+                0,
+                Some(2.0),
+                &ScoreAggregation::Max,
+                &Retrieval::Sparse,
+                &config
+            )
+            .is_ok()
+        );
+        assert!(
+            validate(
+                10,
+                0,
+                Some(f32::NAN),
+                &ScoreAggregation::Max,
+                &Retrieval::Dense,
+                &config
+            )
+            .is_err()
+        );
+        assert!(
+            validate(
+                10,
+                0,
+                Some(0.5),
+                &ScoreAggregation::Max,
+                &Retrieval::Rrf { k: 60 },
+                &config
+            )
+            .is_err()
+        );
+        assert!(
+            validate(
+                10,
+                0,
+                None,
+                &ScoreAggregation::Max,
+                &Retrieval::Rrf { k: 60 },
+                &config
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn validate_rejects_a_threshold_with_other_aggregations() {
+        let config = PagingConfig::default();
+        let mean = ScoreAggregation::MeanTopN { n: 3 };
+
+        assert!(validate(10, 0, Some(0.5), &mean, &Retrieval::Dense, &config).is_err());
+        assert!(
+            validate(
+                10,
+                0,
+                Some(0.5),
+                &ScoreAggregation::Sum,
+                &Retrieval::Dense,
+                &config
+            )
+            .is_err()
+        );
+        assert!(validate(10, 0, None, &mean, &Retrieval::Dense, &config).is_ok());
+        assert!(
+            validate(
+                10,
+                0,
+                None,
+                &ScoreAggregation::Sum,
+                &Retrieval::Sparse,
+                &config
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn window_covers_the_page_and_one_more() {
+        assert_eq!(window(0, 10), 11);
+        assert_eq!(window(20, 10), 31);
+        assert_eq!(window(u64::MAX, 10), u64::MAX);
+    }
+
+    #[test]
+    fn first_page_points_at_the_next() {
+        let (page, next) = page(results(11), 0, 10);
+
+        assert_eq!(ids(&page), (0..10).collect::<Vec<_>>());
+        assert_eq!(next, Some(10));
+    }
+
+    #[test]
+    fn last_page_has_no_next() {
+        let (page, next) = page(results(15), 10, 10);
+
+        assert_eq!(ids(&page), (10..15).collect::<Vec<_>>());
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn exactly_full_page_has_no_next() {
+        let (page, next) = page(results(10), 0, 10);
+
+        assert_eq!(page.len(), 10);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn offset_past_the_results_is_empty() {
+        let (page, next) = page(results(5), 10, 10);
+
+        assert!(page.is_empty());
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn zero_top_k_is_an_empty_page() {
+        let (page, next) = page(results(5), 0, 0);
+
+        assert!(page.is_empty());
+        assert_eq!(next, None);
+    }
+}
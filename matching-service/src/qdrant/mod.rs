@@ -1,7 +1,42 @@
+use crate::aggregation;
 use crate::config::Config;
 use crate::error::MatcherError;
 use qdrant_client::Qdrant;
-use qdrant_client::qdrant::{SearchPoints, WithPayloadSelector, with_payload_selector};
+use qdrant_client::qdrant::{
+    Condition, Filter, PayloadExcludeSelector, PayloadIncludeSelector, PointGroup, PointId,
+    RecommendPointGroups, ScrollPointsBuilder, SearchPointGroups, SparseIndices,
+    WithPayloadSelector, value::Kind, vectors_config, with_payload_selector,
+};
+use shared_types::{DenseVector, SparseVector};
+use std::collections::HashMap;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Points fetched per page when listing the chunks of documents
+const SCROLL_PAGE_SIZE: u32 = 256;
+
+/// The vector a search compares points against
+#[derive(Clone)]
+pub enum QueryVector {
+    /// Embedding, compared with the collection's default dense vector
+    Dense(DenseVector),
+    /// Lexical term weights, compared with the named sparse vector
+    Sparse { name: String, vector: SparseVector },
+}
+
+/// Everything a grouped search needs besides the query vector
+#[derive(Clone)]
+pub struct GroupSearch {
+    /// Documents to return
+    pub limit: u64,
+    /// Best chunks to return per document
+    pub group_size: u32,
+    pub filter: Option<Filter>,
+    /// Minimum chunk score, applied by Qdrant
+    pub score_threshold: Option<f32>,
+    /// Load the chunk text into the payload
+    pub with_text: bool,
+}
 
 /// Create a Qdrant client from configuration
 pub async fn create_client(config: &Config) -> Result<Qdrant, MatcherError> {
@@ -10,27 +45,215 @@ pub async fn create_client(config: &Config) -> Result<Qdrant, MatcherError> {
         .map_err(|e| MatcherError::QdrantSearchError(e.to_string()))
 }
 
-/// Search for the nearest vectors in the collection
-pub async fn search_nearest(
+/// Look up the dense vector size configured for the collection
+///
+/// Returns `None` when the collection uses named vectors, where there is no
+/// single size to compare against.
+pub async fn collection_vector_size(
     client: &Qdrant,
     collection_name: &str,
-    query_vector: Vec<f32>,
-    top_k: u64,
-) -> Result<Vec<qdrant_client::qdrant::ScoredPoint>, MatcherError> {
-    let request = SearchPoints {
+) -> Result<Option<u64>, MatcherError> {
+    let info = client
+        .collection_info(collection_name)
+        .await
+        .map_err(|e| MatcherError::QdrantSearchError(e.to_string()))?;
+
+    let vectors_config = info
+        .result
+        .and_then(|info| info.config)
+        .and_then(|config| config.params)
+        .and_then(|params| params.vectors_config)
+        .and_then(|vectors| vectors.config);
+
+    Ok(match vectors_config {
+        Some(vectors_config::Config::Params(params)) => Some(params.size),
+        _ => None,
+    })
+}
+
+/// Search for the nearest chunks, grouped by their parent document
+///
+/// Returns at most `limit` documents with up to `group_size` of their best
+/// chunks each, optionally restricted by a payload filter and a minimum
+/// score. The chunk text is left out of the payload unless `with_text` is set.
+pub async fn search_groups(
+    client: &Qdrant,
+    collection_name: &str,
+    query_vector: QueryVector,
+    search: GroupSearch,
+) -> Result<Vec<PointGroup>, MatcherError> {
+    let GroupSearch {
+        limit,
+        group_size,
+        filter,
+        score_threshold,
+        with_text,
+    } = search;
+    let (vector, vector_name, sparse_indices) = match query_vector {
+        QueryVector::Dense(vector) => (vector, None, None),
+        QueryVector::Sparse { name, vector } => (
+            vector.values,
+            Some(name),
+            Some(SparseIndices {
+                data: vector.indices,
+            }),
+        ),
+    };
+
+    let request = SearchPointGroups {
         collection_name: collection_name.to_string(),
-        vector: query_vector,
-        limit: top_k,
-        with_payload: Some(WithPayloadSelector {
-            selector_options: Some(with_payload_selector::SelectorOptions::Enable(true)),
-        }),
+        vector,
+        vector_name,
+        sparse_indices,
+        filter,
+        limit: u32::try_from(limit).unwrap_or(u32::MAX),
+        score_threshold,
+        group_by: aggregation::GROUP_BY_FIELD.to_string(),
+        group_size,
+        with_payload: Some(payload_selector(with_text)),
         ..Default::default()
     };
 
     let response = client
-        .search_points(request)
+        .search_groups(request)
         .await
         .map_err(|e| MatcherError::QdrantSearchError(e.to_string()))?;
 
-    Ok(response.result)
+    Ok(response
+        .result
+        .map(|result| result.groups)
+        .unwrap_or_default())
+}
+
+/// Count the documents with a chunk scoring at least `score_threshold`, up to `limit`
+///
+/// Qdrant cannot count by score, so this runs the grouped search with one
+/// chunk per document and counts the groups. The count saturates at `limit`.
+pub async fn count_groups(
+    client: &Qdrant,
+    collection_name: &str,
+    query_vector: QueryVector,
+    filter: Option<Filter>,
+    score_threshold: f32,
+    limit: u64,
+) -> Result<u64, MatcherError> {
+    let groups = search_groups(
+        client,
+        collection_name,
+        query_vector,
+        GroupSearch {
+            limit,
+            group_size: 1,
+            filter,
+            score_threshold: Some(score_threshold),
+            with_text: false,
+        },
+    )
+    .await?;
+
+    Ok(groups.len() as u64)
+}
+
+/// Recommend chunks like the `positive` points and unlike the `negative` ones,
+/// grouped by their parent document
+///
+/// Qdrant compares candidates with the stored vectors of the example points,
+/// averaging them into a single query, so nothing is re-embedded. Otherwise
+/// behaves like [`search_groups`].
+pub async fn recommend_groups(
+    client: &Qdrant,
+    collection_name: &str,
+    positive: Vec<PointId>,
+    negative: Vec<PointId>,
+    search: GroupSearch,
+) -> Result<Vec<PointGroup>, MatcherError> {
+    let request = RecommendPointGroups {
+        collection_name: collection_name.to_string(),
+        positive,
+        negative,
+        filter: search.filter,
+        limit: u32::try_from(search.limit).unwrap_or(u32::MAX),
+        score_threshold: search.score_threshold,
+        group_by: aggregation::GROUP_BY_FIELD.to_string(),
+        group_size: search.group_size,
+        with_payload: Some(payload_selector(search.with_text)),
+        ..Default::default()
+    };
+
+    let response = client
+        .recommend_groups(request)
+        .await
+        .map_err(|e| MatcherError::QdrantSearchError(e.to_string()))?;
+
+    Ok(response
+        .result
+        .map(|result| result.groups)
+        .unwrap_or_default())
+}
+
+/// Point IDs of every indexed chunk of the given documents, by document
+///
+/// Documents without indexed chunks are absent from the map.
+pub async fn document_points(
+    client: &Qdrant,
+    collection_name: &str,
+    documents: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<PointId>>, MatcherError> {
+    let filter = Filter::must([Condition::matches(
+        aggregation::GROUP_BY_FIELD,
+        documents.iter().map(Uuid::to_string).collect::<Vec<_>>(),
+    )]);
+
+    let mut points: HashMap<Uuid, Vec<PointId>> = HashMap::new();
+    let mut offset: Option<PointId> = None;
+    loop {
+        let mut request = ScrollPointsBuilder::new(collection_name)
+            .filter(filter.clone())
+            .limit(SCROLL_PAGE_SIZE)
+            .with_payload(PayloadIncludeSelector::new(vec![
+                aggregation::GROUP_BY_FIELD.to_string(),
+            ]));
+        if let Some(offset) = offset.take() {
+            request = request.offset(offset);
+        }
+
+        let response = client
+            .scroll(request)
+            .await
+            .map_err(|e| MatcherError::QdrantSearchError(e.to_string()))?;
+
+        for point in response.result {
+            let document = match point
+                .payload
+                .get(aggregation::GROUP_BY_FIELD)
+                .and_then(|value| value.kind.as_ref())
+            {
+                Some(Kind::StringValue(id)) => Uuid::from_str(id).ok(),
+                _ => None,
+            };
+            if let (Some(document), Some(id)) = (document, point.id) {
+                points.entry(document).or_default().push(id);
+            }
+        }
+
+        match response.next_page_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    Ok(points)
+}
+
+/// Load the whole payload, or everything except the chunk text
+fn payload_selector(with_text: bool) -> WithPayloadSelector {
+    WithPayloadSelector {
+        selector_options: Some(if with_text {
+            with_payload_selector::SelectorOptions::Enable(true)
+        } else {
+            with_payload_selector::SelectorOptions::Exclude(PayloadExcludeSelector {
+                fields: vec![aggregation::TEXT_FIELD.to_string()],
+            })
+        }),
+    }
 }
@@ -0,0 +1,91 @@
+use crate::error::MatcherError;
+use shared_embedder::SharedCrossEncoder;
+use shared_types::MatchResult;
+
+/// Cross-encoder stage rescoring the best retrieved documents
+pub struct Reranker {
+    pub cross_encoder: SharedCrossEncoder,
+    /// Documents retrieved and rescored per query; pages end within them
+    pub candidates: u64,
+}
+
+/// Pick the reranker for a query: the query's choice, else on whenever one is configured
+pub fn select(
+    requested: Option<bool>,
+    reranker: Option<&Reranker>,
+) -> Result<Option<&Reranker>, MatcherError> {
+    match (requested, reranker) {
+        (Some(true), None) => Err(MatcherError::InvalidQuery(
+            "rerank requested but no cross-encoder is configured".to_string(),
+        )),
+        (Some(false), _) => Ok(None),
+        (_, reranker) => Ok(reranker),
+    }
+}
+
+impl Reranker {
+    /// Rescore every candidate and keep the `limit` most relevant
+    ///
+    /// Each retrieved chunk is scored together with the query, and a document
+    /// is as relevant as its most relevant chunk. Documents without stored
+    /// chunk text (indexed before text was stored) cannot be scored; they
+    /// follow the reranked ones in retrieval order.
+    pub async fn rerank(
+        &self,
+        query: &str,
+        results: Vec<MatchResult>,
+        limit: u64,
+    ) -> Result<Vec<MatchResult>, MatcherError> {
+        let (owners, passages): (Vec<usize>, Vec<String>) = results
+            .iter()
+            .enumerate()
+            .flat_map(|(owner, result)| {
+                result
+                    .chunks
+                    .iter()
+                    .filter_map(move |chunk| chunk.text.clone().map(|text| (owner, text)))
+            })
+            .unzip();
+
+        let scores = if passages.is_empty() {
+            Vec::new()
+        } else {
+            shared_embedder::score_passages(&self.cross_encoder, query.to_string(), passages)
+                .await?
+        };
+
+        let mut best: Vec<Option<f32>> = vec![None; results.len()];
+        for (owner, score) in owners.into_iter().zip(scores) {
+            best[owner] = Some(best[owner].map_or(score, |best| best.max(score)));
+        }
+
+        let (mut reranked, unscored): (Vec<MatchResult>, Vec<MatchResult>) = results
+            .into_iter()
+            .zip(best)
+            .map(|(result, rerank_score)| match rerank_score {
+                Some(rerank_score) => MatchResult {
+                    score: rerank_score,
+                    retrieval_score: Some(result.score),
+                    rerank_score: Some(rerank_score),
+                    ..result
+                },
+                None => result,
+            })
+            .partition(|result| result.rerank_score.is_some());
+        reranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        let mut results = reranked;
+        results.extend(unscored);
+        results.truncate(usize::try_from(limit).unwrap_or(usize::MAX));
+
+        // The texts were only loaded for scoring
+        for chunk in results
+            .iter_mut()
+            .flat_map(|result| result.chunks.iter_mut())
+        {
+            chunk.text = None;
+        }
+
+        Ok(results)
+    }
+}
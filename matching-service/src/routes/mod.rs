@@ -9,5 +9,7 @@ pub fn create_router(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/health", get(handlers::health_check))
         .route("/match", post(handlers::find_matches))
+        .route("/match/similar", post(handlers::find_similar))
+        .route("/match/batch", post(handlers::find_matches_batch))
         .with_state(state)
 }
@@ -0,0 +1,85 @@
+use crate::aggregation;
+use crate::config::SimilarConfig;
+use crate::error::MatcherError;
+use qdrant_client::qdrant::{Condition, Filter, PointId};
+use shared_types::SimilarQuery;
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+/// Reject seed lists that cannot produce a recommendation
+pub fn validate(query: &SimilarQuery, config: &SimilarConfig) -> Result<(), MatcherError> {
+    if query.positive.is_empty() {
+        return Err(MatcherError::InvalidQuery(
+            "at least one positive document is required".to_string(),
+        ));
+    }
+
+    let seeds = seeds(query);
+    if seeds.len() > config.max_seed_documents {
+        return Err(MatcherError::InvalidQuery(format!(
+            "at most {} positive and negative documents are allowed, got {}",
+            config.max_seed_documents,
+            seeds.len()
+        )));
+    }
+
+    if let Some(id) = query.negative.iter().find(|id| query.positive.contains(id)) {
+        return Err(MatcherError::InvalidQuery(format!(
+            "document {} cannot be both positive and negative",
+            id
+        )));
+    }
+
+    Ok(())
+}
+
+/// Every distinct positive and negative document, in a stable order
+pub fn seeds(query: &SimilarQuery) -> Vec<Uuid> {
+    query
+        .positive
+        .iter()
+        .chain(&query.negative)
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Split the chunk points of the seeds into positive and negative examples
+///
+/// Fails with `DocumentNotFound` listing every seed that has no indexed chunk.
+pub fn example_points(
+    query: &SimilarQuery,
+    mut points: HashMap<Uuid, Vec<PointId>>,
+) -> Result<(Vec<PointId>, Vec<PointId>), MatcherError> {
+    let missing: Vec<String> = seeds(query)
+        .into_iter()
+        .filter(|id| !points.contains_key(id))
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(MatcherError::DocumentNotFound(format!(
+            "not indexed: {}",
+            missing.join(", ")
+        )));
+    }
+
+    let mut take = |ids: &[Uuid]| -> Vec<PointId> {
+        ids.iter()
+            .flat_map(|id| points.remove(id).unwrap_or_default())
+            .collect()
+    };
+    let positive = take(&query.positive);
+    let negative = take(&query.negative);
+    Ok((positive, negative))
+}
+
+/// Add a condition keeping the seed documents themselves out of the results
+pub fn exclude_seeds(filter: Option<Filter>, seeds: &[Uuid]) -> Filter {
+    let mut filter = filter.unwrap_or_default();
+    filter.must_not.push(Condition::matches(
+        aggregation::GROUP_BY_FIELD,
+        seeds.iter().map(Uuid::to_string).collect::<Vec<_>>(),
+    ));
+    filter
+}
@@ -1,21 +1,29 @@
 use crate::config::Config;
+use crate::rerank::Reranker;
 use qdrant_client::Qdrant;
-use rust_bert::pipelines::sentence_embeddings::SentenceEmbeddingsModel;
-use std::sync::Mutex;
+use shared_embedder::SharedEmbedder;
 
 /// Shared application state holding heavy clients
 pub struct AppState {
-    /// Mutex needed for thread-safe access to the C++ LibTorch backend
-    pub model: Mutex<SentenceEmbeddingsModel>,
+    /// Sentence embedding backend (same implementation as the vector-worker)
+    pub embedder: SharedEmbedder,
     pub qdrant: Qdrant,
+    /// Cross-encoder stage, when configured
+    pub reranker: Option<Reranker>,
     pub config: Config,
 }
 
 impl AppState {
-    pub fn new(model: SentenceEmbeddingsModel, qdrant: Qdrant, config: Config) -> Self {
+    pub fn new(
+        embedder: SharedEmbedder,
+        qdrant: Qdrant,
+        reranker: Option<Reranker>,
+        config: Config,
+    ) -> Self {
         Self {
-            model: Mutex::new(model),
+            embedder,
             qdrant,
+            reranker,
             config,
         }
     }
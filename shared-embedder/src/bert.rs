@@ -0,0 +1,159 @@
+use crate::{Embedder, EmbedderError, PretrainedModel};
+use rust_bert::pipelines::sentence_embeddings::{
+    SentenceEmbeddingsBuilder, SentenceEmbeddingsModel,
+};
+use shared_types::DenseVector;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Files a local sentence-transformers directory must contain for rust-bert
+const REQUIRED_MODEL_FILES: &[&str] = &[
+    "modules.json",
+    "config.json",
+    "rust_model.ot",
+    "1_Pooling/config.json",
+];
+
+/// Tokenizer vocabularies; which one is needed depends on the architecture
+const TOKENIZER_FILES: &[&str] = &[
+    "vocab.txt",
+    "vocab.json",
+    "spiece.model",
+    "sentencepiece.bpe.model",
+];
+
+/// Embedder backed by a rust-bert sentence-transformer model
+///
+/// Loads a converted model from a local directory when one is configured,
+/// otherwise downloads the selected pretrained model. The default,
+/// AllMiniLmL6V2, provides a good balance of:
+/// - Fast inference speed
+/// - Small memory footprint
+/// - Good quality embeddings (384 dimensions)
+pub struct BertEmbedder {
+    /// Mutex needed for thread-safe access to the C++ LibTorch backend
+    model: Mutex<SentenceEmbeddingsModel>,
+    dimension: usize,
+    model_id: String,
+}
+
+impl BertEmbedder {
+    /// Load a custom model from `model_dir`, or download `pretrained` on first use
+    ///
+    /// A local model must come with its `model_id`: nothing in the directory
+    /// reliably names the model, and the id must equal the indexing-worker's
+    /// `collection_model_id` for its vectors to be indexed.
+    ///
+    /// Blocking; call from `spawn_blocking` when inside an async context.
+    pub fn new(
+        pretrained: PretrainedModel,
+        model_dir: Option<&Path>,
+        model_id: Option<String>,
+    ) -> Result<Self, EmbedderError> {
+        let (model, model_id) = match model_dir {
+            Some(dir) => {
+                let model_id = model_id.ok_or_else(|| {
+                    EmbedderError::ModelInitError(format!(
+                        "embedder.model_id is required with model_dir '{}'; set it to the model \
+                         the directory holds, e.g. sentence-transformers/all-MiniLM-L6-v2",
+                        dir.display()
+                    ))
+                })?;
+                verify_model_dir(dir, REQUIRED_MODEL_FILES, TOKENIZER_FILES)?;
+                tracing::info!(model_dir = %dir.display(), "Loading BERT model from local directory...");
+
+                let model = SentenceEmbeddingsBuilder::local(dir)
+                    .create_model()
+                    .map_err(|e| EmbedderError::ModelInitError(e.to_string()))?;
+                (model, model_id)
+            }
+            None => {
+                tracing::info!(
+                    model = pretrained.model_id(),
+                    "Loading BERT model (this may take time on first run)..."
+                );
+
+                let model = SentenceEmbeddingsBuilder::remote(pretrained.model_type())
+                    .create_model()
+                    .map_err(|e| EmbedderError::ModelInitError(e.to_string()))?;
+                (model, pretrained.model_id().to_string())
+            }
+        };
+
+        // Probe the model once so the dimension is known without hard-coding it
+        let dimension = model
+            .encode(&["dimension probe"])
+            .map_err(|e| EmbedderError::ModelInitError(e.to_string()))?
+            .first()
+            .map(Vec::len)
+            .ok_or_else(|| EmbedderError::ModelInitError("Model produced no vector".into()))?;
+
+        Ok(Self {
+            model: Mutex::new(model),
+            dimension,
+            model_id,
+        })
+    }
+}
+
+impl Embedder for BertEmbedder {
+    fn embed(&self, texts: &[String]) -> Result<Vec<DenseVector>, EmbedderError> {
+        let model = self
+            .model
+            .lock()
+            .map_err(|_| EmbedderError::InferenceError("Model mutex poisoned".into()))?;
+
+        model
+            .encode(texts)
+            .map_err(|e| EmbedderError::InferenceError(e.to_string()))
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+}
+
+/// Check that a local model directory has everything rust-bert needs
+///
+/// rust-bert's own errors for a half-copied directory are hard to read, so
+/// list every missing file up front. At least one of `tokenizer_files` must
+/// be present.
+pub(crate) fn verify_model_dir(
+    dir: &Path,
+    required_files: &[&str],
+    tokenizer_files: &[&str],
+) -> Result<(), EmbedderError> {
+    if !dir.is_dir() {
+        return Err(EmbedderError::ModelInitError(format!(
+            "Model directory '{}' does not exist",
+            dir.display()
+        )));
+    }
+
+    let mut missing: Vec<String> = required_files
+        .iter()
+        .filter(|file| !dir.join(file).is_file())
+        .map(|file| file.to_string())
+        .collect();
+
+    if !tokenizer_files.iter().any(|file| dir.join(file).is_file()) {
+        missing.push(format!(
+            "tokenizer vocabulary (one of {})",
+            tokenizer_files.join(", ")
+        ));
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(EmbedderError::ModelInitError(format!(
+            "Model directory '{}' is missing required files: {}",
+            dir.display(),
+            missing.join(", ")
+        )))
+    }
+}
@@ -0,0 +1,227 @@
+use crate::EmbedderError;
+use crate::hashing::fnv1a;
+use serde::Deserialize;
+use shared_types::SparseVector;
+use std::collections::BTreeMap;
+
+/// Words too common to say anything about a document
+const STOP_WORDS: [&str; 36] = [
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "has", "have", "in",
+    "is", "it", "its", "of", "on", "or", "our", "so", "that", "the", "their", "this", "to", "was",
+    "we", "were", "will", "with", "you", "your", "i", "my",
+];
+
+/// BM25 term-frequency saturation and length normalisation parameters
+///
+/// ```yaml
+/// sparse:
+///   k1: 1.2
+///   b: 0.75
+///   avg_doc_tokens: 100
+/// ```
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct Bm25Config {
+    /// How quickly repeated occurrences of a term stop adding weight
+    pub k1: f32,
+    /// How strongly weights are normalised by text length (0 = not at all, 1 = fully)
+    pub b: f32,
+    /// Typical number of tokens of an encoded text (a chunk, for the vector worker)
+    pub avg_doc_tokens: f32,
+}
+
+impl Default for Bm25Config {
+    fn default() -> Self {
+        Self {
+            k1: 1.2,
+            b: 0.75,
+            avg_doc_tokens: 100.0,
+        }
+    }
+}
+
+/// Lexical encoder producing BM25-weighted sparse vectors over hashed terms
+///
+/// Terms are hashed into the `u32` index space, so there is no vocabulary
+/// to build or ship. Document vectors carry only the term-frequency half of
+/// BM25: the inverse document frequency depends on the whole corpus and is
+/// applied by Qdrant at search time (sparse vector `modifier: idf`).
+pub struct Bm25Encoder {
+    config: Bm25Config,
+}
+
+impl Bm25Encoder {
+    pub fn new(config: Bm25Config) -> Result<Self, EmbedderError> {
+        if config.k1 < 0.0 || !(0.0..=1.0).contains(&config.b) || config.avg_doc_tokens <= 0.0 {
+            return Err(EmbedderError::ModelInitError(format!(
+                "BM25 requires k1 >= 0, 0 <= b <= 1 and avg_doc_tokens > 0, got {:?}",
+                config
+            )));
+        }
+
+        Ok(Self { config })
+    }
+
+    /// Encode a text to be searched: saturated, length-normalised term frequencies
+    pub fn encode_document(&self, text: &str) -> SparseVector {
+        let frequencies = term_frequencies(text);
+        let length: f32 = frequencies.values().sum();
+        let Bm25Config {
+            k1,
+            b,
+            avg_doc_tokens,
+        } = self.config;
+        let norm = k1 * (1.0 - b + b * length / avg_doc_tokens);
+
+        let (indices, values) = frequencies
+            .into_iter()
+            .map(|(index, tf)| (index, tf * (k1 + 1.0) / (tf + norm)))
+            .unzip();
+
+        SparseVector { indices, values }
+    }
+}
+
+/// Encode a search query: every distinct term once, with weight 1
+///
+/// Scoring it against document vectors with Qdrant's IDF modifier yields
+/// the BM25 score of each document.
+pub fn encode_query(text: &str) -> SparseVector {
+    let indices: Vec<u32> = term_frequencies(text).into_keys().collect();
+    let values = vec![1.0; indices.len()];
+
+    SparseVector { indices, values }
+}
+
+/// Occurrences of each hashed term, ordered by index as Qdrant expects
+///
+/// Hash collisions merge two terms into one dimension; with a 32-bit space
+/// they are rare enough not to matter for ranking.
+fn term_frequencies(text: &str) -> BTreeMap<u32, f32> {
+    let mut frequencies = BTreeMap::new();
+    for token in tokenize(text) {
+        *frequencies
+            .entry(fnv1a(token.as_bytes()) as u32)
+            .or_insert(0.0) += 1.0;
+    }
+    frequencies
+}
+
+/// Lower-cased words, keeping `+` and `#` so "C++" and "C#" stay distinct from "C"
+///
+/// No stemming: the point of lexical retrieval is to match exact terms
+/// such as "Kubernetes", "CKA" or "COBOL".
+fn tokenize(text: &str) -> impl Iterator<Item = String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '+' || c == '#'))
+        .map(|token| token.trim_start_matches(['+', '#']).to_lowercase())
+        .filter(|token| !token.is_empty() && !STOP_WORDS.contains(&token.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoder() -> Bm25Encoder {
+        Bm25Encoder::new(Bm25Config::default()).unwrap()
+    }
+
+    fn index(term: &str) -> u32 {
+        fnv1a(term.as_bytes()) as u32
+    }
+
+    fn weight(vector: &SparseVector, term: &str) -> Option<f32> {
+        vector
+            .indices
+            .iter()
+            .position(|i| *i == index(term))
+            .map(|position| vector.values[position])
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        for config in [
+            Bm25Config {
+                k1: -0.1,
+                ..Bm25Config::default()
+            },
+            Bm25Config {
+                b: 1.5,
+                ..Bm25Config::default()
+            },
+            Bm25Config {
+                avg_doc_tokens: 0.0,
+                ..Bm25Config::default()
+            },
+        ] {
+            assert!(Bm25Encoder::new(config).is_err());
+        }
+    }
+
+    #[test]
+    fn query_has_each_distinct_term_once_with_weight_one() {
+        let query = encode_query("Rust rust RUST kafka");
+
+        assert_eq!(query.indices.len(), 2);
+        assert_eq!(query.values, vec![1.0, 1.0]);
+        assert!(query.indices.contains(&index("rust")));
+        assert!(query.indices.contains(&index("kafka")));
+    }
+
+    #[test]
+    fn indices_are_sorted() {
+        let vector = encoder().encode_document("zeta alpha kubernetes cobol python go");
+
+        assert!(vector.indices.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(vector.indices.len(), vector.values.len());
+    }
+
+    #[test]
+    fn stop_words_are_dropped() {
+        assert!(encode_query("the and of to").indices.is_empty());
+    }
+
+    #[test]
+    fn keeps_plus_and_hash_inside_terms() {
+        let query = encode_query("C++ C# c");
+
+        assert!(query.indices.contains(&index("c++")));
+        assert!(query.indices.contains(&index("c#")));
+        assert!(query.indices.contains(&index("c")));
+        // Leading symbols are trimmed so "#rust" matches "rust"
+        assert_eq!(encode_query("#rust").indices, vec![index("rust")]);
+    }
+
+    #[test]
+    fn repeated_terms_saturate() {
+        let encoder = encoder();
+        let once = weight(&encoder.encode_document("rust"), "rust").unwrap();
+        let twice = weight(&encoder.encode_document("rust rust"), "rust").unwrap();
+        let many = weight(&encoder.encode_document(&"rust ".repeat(50)), "rust").unwrap();
+
+        assert!(once < twice && twice < many);
+        assert!(many < Bm25Config::default().k1 + 1.0);
+    }
+
+    #[test]
+    fn longer_texts_weigh_a_term_less() {
+        let encoder = encoder();
+        let short = weight(&encoder.encode_document("rust engineer"), "rust").unwrap();
+        let long_text = format!("rust {}", "engineer ".repeat(200));
+        let long = weight(&encoder.encode_document(&long_text), "rust").unwrap();
+
+        assert!(long < short);
+    }
+
+    #[test]
+    fn without_length_normalisation_length_does_not_matter() {
+        let encoder = Bm25Encoder::new(Bm25Config {
+            b: 0.0,
+            ..Bm25Config::default()
+        })
+        .unwrap();
+        let short = weight(&encoder.encode_document("rust engineer"), "rust").unwrap();
+        let long_text = format!("rust {}", "engineer ".repeat(200));
+        let long = weight(&encoder.encode_document(&long_text), "rust").unwrap();
+
+        assert!((long - short).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,142 @@
+#[cfg(feature = "bert")]
+use rust_bert::pipelines::sentence_embeddings::SentenceEmbeddingsModelType;
+use serde::Deserialize;
+
+/// Embedding backend selection, shared by every service that embeds text
+///
+/// ```yaml
+/// embedder:
+///   backend: rust_bert
+///   model: all-MiniLM-L6-v2
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum EmbedderConfig {
+    /// Sentence-transformer model run through rust-bert / LibTorch.
+    RustBert {
+        /// Pretrained model downloaded from Hugging Face.
+        #[serde(default)]
+        model: PretrainedModel,
+        /// Load a custom model from this local directory instead of
+        /// downloading `model`.
+        #[serde(default)]
+        model_dir: Option<String>,
+        /// Identity recorded on vectors produced by a local model, e.g.
+        /// `sentence-transformers/all-MiniLM-L6-v2`. Required with `model_dir`.
+        #[serde(default)]
+        model_id: Option<String>,
+    },
+    /// Deterministic feature-hashing embedder; needs no model weights.
+    Hashing { dimension: usize },
+}
+
+impl Default for EmbedderConfig {
+    fn default() -> Self {
+        EmbedderConfig::RustBert {
+            model: PretrainedModel::default(),
+            model_dir: None,
+            model_id: None,
+        }
+    }
+}
+
+/// Cross-encoder model used to rerank search results
+///
+/// ```yaml
+/// cross_encoder:
+///   model_dir: /models/ms-marco-MiniLM-L-6-v2
+///   max_length: 512
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct CrossEncoderConfig {
+    /// Local directory holding a BERT sequence-classification model
+    /// converted for rust-bert. Cross-encoders are never downloaded.
+    pub model_dir: String,
+    /// Identity reported in logs. Defaults to `local:<directory name>`.
+    #[serde(default)]
+    pub model_id: Option<String>,
+    /// Maximum tokens of a (query, passage) pair; longer pairs are truncated.
+    #[serde(default = "default_max_length")]
+    pub max_length: usize,
+    /// Pairs scored per model call.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Whether the vocabulary is uncased, as for the ms-marco MiniLM models.
+    #[serde(default = "default_lower_case")]
+    pub lower_case: bool,
+}
+
+fn default_max_length() -> usize {
+    512
+}
+
+fn default_batch_size() -> usize {
+    16
+}
+
+fn default_lower_case() -> bool {
+    true
+}
+
+/// Pretrained sentence-transformers supported by rust-bert
+///
+/// Names follow the Hugging Face `sentence-transformers/<name>` repositories.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PretrainedModel {
+    #[serde(rename = "distiluse-base-multilingual-cased")]
+    DistiluseBaseMultilingualCased,
+    #[serde(rename = "bert-base-nli-mean-tokens")]
+    BertBaseNliMeanTokens,
+    #[serde(rename = "all-MiniLM-L12-v2")]
+    AllMiniLmL12V2,
+    #[default]
+    #[serde(rename = "all-MiniLM-L6-v2")]
+    AllMiniLmL6V2,
+    #[serde(rename = "all-distilroberta-v1")]
+    AllDistilrobertaV1,
+    #[serde(rename = "paraphrase-albert-small-v2")]
+    ParaphraseAlbertSmallV2,
+    #[serde(rename = "sentence-t5-base")]
+    SentenceT5Base,
+}
+
+impl PretrainedModel {
+    /// The rust-bert model type used to fetch the weights
+    #[cfg(feature = "bert")]
+    pub fn model_type(self) -> SentenceEmbeddingsModelType {
+        match self {
+            PretrainedModel::DistiluseBaseMultilingualCased => {
+                SentenceEmbeddingsModelType::DistiluseBaseMultilingualCased
+            }
+            PretrainedModel::BertBaseNliMeanTokens => {
+                SentenceEmbeddingsModelType::BertBaseNliMeanTokens
+            }
+            PretrainedModel::AllMiniLmL12V2 => SentenceEmbeddingsModelType::AllMiniLmL12V2,
+            PretrainedModel::AllMiniLmL6V2 => SentenceEmbeddingsModelType::AllMiniLmL6V2,
+            PretrainedModel::AllDistilrobertaV1 => SentenceEmbeddingsModelType::AllDistilrobertaV1,
+            PretrainedModel::ParaphraseAlbertSmallV2 => {
+                SentenceEmbeddingsModelType::ParaphraseAlbertSmallV2
+            }
+            PretrainedModel::SentenceT5Base => SentenceEmbeddingsModelType::SentenceT5Base,
+        }
+    }
+
+    /// Stable model identity, e.g. `sentence-transformers/all-MiniLM-L6-v2`
+    pub fn model_id(self) -> &'static str {
+        match self {
+            PretrainedModel::DistiluseBaseMultilingualCased => {
+                "sentence-transformers/distiluse-base-multilingual-cased"
+            }
+            PretrainedModel::BertBaseNliMeanTokens => {
+                "sentence-transformers/bert-base-nli-mean-tokens"
+            }
+            PretrainedModel::AllMiniLmL12V2 => "sentence-transformers/all-MiniLM-L12-v2",
+            PretrainedModel::AllMiniLmL6V2 => "sentence-transformers/all-MiniLM-L6-v2",
+            PretrainedModel::AllDistilrobertaV1 => "sentence-transformers/all-distilroberta-v1",
+            PretrainedModel::ParaphraseAlbertSmallV2 => {
+                "sentence-transformers/paraphrase-albert-small-v2"
+            }
+            PretrainedModel::SentenceT5Base => "sentence-transformers/sentence-t5-base",
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::bert::verify_model_dir;
+use crate::{CrossEncoderConfig, EmbedderError};
+use rust_bert::Config;
+use rust_bert::bert::{BertConfig, BertForSequenceClassification};
+use rust_tokenizers::tokenizer::{BertTokenizer, MultiThreadedTokenizer, TruncationStrategy};
+use rust_tokenizers::vocab::Vocab;
+use std::iter::repeat_n;
+use std::path::Path;
+use std::sync::Mutex;
+use tch::{Device, Kind, Tensor, nn};
+
+/// Files a local cross-encoder directory must contain
+const REQUIRED_MODEL_FILES: &[&str] = &["config.json", "rust_model.ot"];
+
+/// Vocabulary of the BERT tokenizer
+const TOKENIZER_FILES: &[&str] = &["vocab.txt"];
+
+/// Relevance model scoring (query, passage) pairs jointly
+///
+/// Unlike the bi-encoder embedders, query and passage go through the
+/// transformer together, so attention spans both texts. This is far more
+/// precise and far slower, so it only rescores a few candidates. Expects a
+/// BERT sequence-classification model such as
+/// `cross-encoder/ms-marco-MiniLM-L-6-v2`, converted for rust-bert.
+pub struct CrossEncoder {
+    /// Mutex needed for thread-safe access to the C++ LibTorch backend
+    model: Mutex<BertForSequenceClassification>,
+    /// Owns the weights the model refers to
+    _var_store: nn::VarStore,
+    tokenizer: BertTokenizer,
+    pad_token_id: i64,
+    device: Device,
+    max_length: usize,
+    batch_size: usize,
+    model_id: String,
+}
+
+impl CrossEncoder {
+    /// Load the model from the configured local directory
+    ///
+    /// Blocking; call from `spawn_blocking` when inside an async context.
+    pub fn new(config: &CrossEncoderConfig) -> Result<Self, EmbedderError> {
+        if config.max_length < 3 || config.batch_size == 0 {
+            return Err(EmbedderError::ModelInitError(format!(
+                "Cross-encoder requires max_length >= 3 and batch_size >= 1, got {} and {}",
+                config.max_length, config.batch_size
+            )));
+        }
+
+        let dir = Path::new(&config.model_dir);
+        verify_model_dir(dir, REQUIRED_MODEL_FILES, TOKENIZER_FILES)?;
+        tracing::info!(model_dir = %dir.display(), "Loading cross-encoder from local directory...");
+
+        let device = Device::cuda_if_available();
+        let mut var_store = nn::VarStore::new(device);
+        let model_config = BertConfig::from_file(dir.join("config.json"));
+        let model = BertForSequenceClassification::new(var_store.root(), &model_config)
+            .map_err(|e| EmbedderError::ModelInitError(e.to_string()))?;
+        var_store
+            .load(dir.join("rust_model.ot"))
+            .map_err(|e| EmbedderError::ModelInitError(e.to_string()))?;
+
+        // Uncased BERT vocabularies also strip accents
+        let tokenizer =
+            BertTokenizer::from_file(dir.join("vocab.txt"), config.lower_case, config.lower_case)
+                .map_err(|e| EmbedderError::ModelInitError(e.to_string()))?;
+        let vocab = MultiThreadedTokenizer::vocab(&tokenizer);
+        let pad_token_id = vocab.token_to_id(vocab.get_pad_value());
+
+        let model_id = config.model_id.clone().unwrap_or_else(|| {
+            let name = dir.file_name().unwrap_or(dir.as_os_str());
+            format!("local:{}", name.to_string_lossy())
+        });
+
+        Ok(Self {
+            model: Mutex::new(model),
+            _var_store: var_store,
+            tokenizer,
+            pad_token_id,
+            device,
+            max_length: config.max_length,
+            batch_size: config.batch_size,
+            model_id,
+        })
+    }
+
+    /// Relevance of each passage to the query, in (0, 1), in input order
+    ///
+    /// Passages longer than `max_length` tokens (together with the query)
+    /// are truncated, so only their beginning is judged.
+    pub fn score(&self, query: &str, passages: &[String]) -> Result<Vec<f32>, EmbedderError> {
+        let mut scores = Vec::with_capacity(passages.len());
+        for batch in passages.chunks(self.batch_size) {
+            scores.extend(self.score_batch(query, batch)?);
+        }
+        Ok(scores)
+    }
+
+    /// Stable identifier of the underlying model.
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn score_batch(&self, query: &str, passages: &[String]) -> Result<Vec<f32>, EmbedderError> {
+        let pairs: Vec<(&str, &str)> = passages
+            .iter()
+            .map(|passage| (query, passage.as_str()))
+            .collect();
+        let inputs = MultiThreadedTokenizer::encode_pair_list(
+            &self.tokenizer,
+            &pairs,
+            self.max_length,
+            &TruncationStrategy::LongestFirst,
+            0,
+        );
+
+        // Pad every pair to the longest one; the mask hides the padding
+        let length = inputs.iter().map(|i| i.token_ids.len()).max().unwrap_or(0);
+        let mut token_ids = Vec::with_capacity(pairs.len() * length);
+        let mut segment_ids = Vec::with_capacity(pairs.len() * length);
+        let mut mask = Vec::with_capacity(pairs.len() * length);
+        for input in inputs {
+            let padding = length - input.token_ids.len();
+            mask.extend(repeat_n(1, input.token_ids.len()));
+            mask.extend(repeat_n(0, padding));
+            segment_ids.extend(input.segment_ids.into_iter().map(i64::from));
+            segment_ids.extend(repeat_n(0, padding));
+            token_ids.extend(input.token_ids);
+            token_ids.extend(repeat_n(self.pad_token_id, padding));
+        }
+
+        let shape = [pairs.len() as i64, length as i64];
+        let tensor = |values: &[i64]| {
+            Tensor::from_slice(values)
+                .view(shape)
+                .to_device(self.device)
+        };
+        let (token_ids, segment_ids, mask) =
+            (tensor(&token_ids), tensor(&segment_ids), tensor(&mask));
+
+        let model = self
+            .model
+            .lock()
+            .map_err(|_| EmbedderError::InferenceError("Model mutex poisoned".into()))?;
+        let logits = tch::no_grad(|| {
+            model
+                .forward_t(
+                    Some(&token_ids),
+                    Some(&mask),
+                    Some(&segment_ids),
+                    None,
+                    None,
+                    false,
+                )
+                .logits
+        });
+
+        // Either a single relevance logit (the ms-marco models) or
+        // [irrelevant, relevant] class logits
+        let relevance = if logits.size().last() == Some(&1) {
+            logits.squeeze_dim(1).sigmoid()
+        } else {
+            logits.softmax(-1, Kind::Float).select(1, -1)
+        };
+
+        Vec::<f32>::try_from(&relevance.to_kind(Kind::Float))
+            .map_err(|e| EmbedderError::InferenceError(e.to_string()))
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{CrossEncoderConfig, Embedder, EmbedderError, PretrainedModel};
+use shared_types::DenseVector;
+use std::convert::Infallible;
+use std::path::Path;
+
+/// Stand-in for the rust-bert embedder when built without the `bert` feature
+///
+/// Keeps the crate's API the same either way; it can never be constructed,
+/// so selecting the `rust_bert` backend fails at startup.
+pub struct BertEmbedder {
+    never: Infallible,
+}
+
+impl BertEmbedder {
+    pub fn new(
+        _pretrained: PretrainedModel,
+        _model_dir: Option<&Path>,
+        _model_id: Option<String>,
+    ) -> Result<Self, EmbedderError> {
+        Err(unavailable("rust_bert embedder backend"))
+    }
+}
+
+impl Embedder for BertEmbedder {
+    fn embed(&self, _texts: &[String]) -> Result<Vec<DenseVector>, EmbedderError> {
+        match self.never {}
+    }
+
+    fn dimension(&self) -> usize {
+        match self.never {}
+    }
+
+    fn model_id(&self) -> &str {
+        match self.never {}
+    }
+}
+
+/// Stand-in for the cross-encoder when built without the `bert` feature
+pub struct CrossEncoder {
+    never: Infallible,
+}
+
+impl CrossEncoder {
+    pub fn new(_config: &CrossEncoderConfig) -> Result<Self, EmbedderError> {
+        Err(unavailable("cross-encoder"))
+    }
+
+    pub fn score(&self, _query: &str, _passages: &[String]) -> Result<Vec<f32>, EmbedderError> {
+        match self.never {}
+    }
+
+    pub fn model_id(&self) -> &str {
+        match self.never {}
+    }
+}
+
+fn unavailable(what: &str) -> EmbedderError {
+    EmbedderError::ModelInitError(format!(
+        "the {} needs shared-embedder built with the `bert` feature",
+        what
+    ))
+}
@@ -0,0 +1,19 @@
+use std::fmt;
+
+/// Errors raised while loading or running an embedding model
+#[derive(Debug)]
+pub enum EmbedderError {
+    ModelInitError(String),
+    InferenceError(String),
+}
+
+impl fmt::Display for EmbedderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmbedderError::ModelInitError(e) => write!(f, "Model initialization error: {}", e),
+            EmbedderError::InferenceError(e) => write!(f, "Inference error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EmbedderError {}
@@ -0,0 +1,125 @@
+use crate::{Embedder, EmbedderError};
+use shared_types::DenseVector;
+
+/// Deterministic embedder based on signed feature hashing
+///
+/// Each lower-cased word is hashed into one of `dimension` buckets and the
+/// resulting term-frequency vector is L2-normalised. It captures lexical
+/// overlap only, but needs no model weights, which makes it suitable for
+/// tests and local development.
+pub struct HashingEmbedder {
+    dimension: usize,
+    model_id: String,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimension: usize) -> Result<Self, EmbedderError> {
+        if dimension == 0 {
+            return Err(EmbedderError::ModelInitError(
+                "Hashing embedder dimension must be greater than zero".into(),
+            ));
+        }
+
+        Ok(Self {
+            dimension,
+            model_id: format!("hashing-{}", dimension),
+        })
+    }
+
+    fn embed_one(&self, text: &str) -> DenseVector {
+        let mut vector = vec![0.0f32; self.dimension];
+
+        for token in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+        {
+            let hash = fnv1a(token.to_lowercase().as_bytes());
+            let bucket = (hash % self.dimension as u64) as usize;
+            // The top bit picks the sign so colliding tokens tend to cancel out
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[bucket] += sign;
+        }
+
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        }
+
+        vector
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn embed(&self, texts: &[String]) -> Result<Vec<DenseVector>, EmbedderError> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+}
+
+/// 64-bit FNV-1a, stable across platforms and releases (unlike `DefaultHasher`)
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embed(embedder: &HashingEmbedder, text: &str) -> DenseVector {
+        embedder.embed(&[text.to_string()]).unwrap().remove(0)
+    }
+
+    #[test]
+    fn rejects_zero_dimension() {
+        assert!(HashingEmbedder::new(0).is_err());
+    }
+
+    #[test]
+    fn vectors_have_the_configured_dimension_and_unit_length() {
+        let embedder = HashingEmbedder::new(64).unwrap();
+        let vector = embed(&embedder, "Rust engineer with Kafka experience");
+
+        assert_eq!(vector.len(), 64);
+        assert_eq!(embedder.dimension(), 64);
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn embedding_is_deterministic_and_case_insensitive() {
+        let embedder = HashingEmbedder::new(128).unwrap();
+
+        assert_eq!(
+            embed(&embedder, "Distributed Systems"),
+            embed(&embedder, "distributed systems")
+        );
+        assert_eq!(embedder.model_id(), "hashing-128");
+    }
+
+    #[test]
+    fn text_without_words_embeds_to_zero() {
+        let embedder = HashingEmbedder::new(16).unwrap();
+
+        assert!(embed(&embedder, " -- ").iter().all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn returns_one_vector_per_input_in_order() {
+        let embedder = HashingEmbedder::new(32).unwrap();
+        let texts = vec!["first".to_string(), "second".to_string()];
+        let vectors = embedder.embed(&texts).unwrap();
+
+        assert_eq!(vectors.len(), 2);
+        assert_eq!(vectors[0], embed(&embedder, "first"));
+        assert_eq!(vectors[1], embed(&embedder, "second"));
+    }
+}
@@ -0,0 +1,144 @@
+//! Sentence embedding backends shared by the vector worker and the matching service.
+//!
+//! Both services embed text through the [`Embedder`] trait so documents and
+//! queries always go through the same code path and land in the same vector space.
+//! Lexical sparse vectors for keyword retrieval are produced the same way on
+//! both sides by the [`bm25`] encoder. The matching service can rerank its
+//! results with a [`CrossEncoder`].
+//!
+//! The rust-bert backends need LibTorch and sit behind the default `bert`
+//! feature. Without it they fail to load, and the [`HashingEmbedder`] is the
+//! only working backend.
+
+#[cfg(feature = "bert")]
+mod bert;
+pub mod bm25;
+mod config;
+#[cfg(feature = "bert")]
+mod cross_encoder;
+#[cfg(not(feature = "bert"))]
+mod disabled;
+mod error;
+mod hashing;
+
+#[cfg(feature = "bert")]
+pub use bert::BertEmbedder;
+pub use bm25::{Bm25Config, Bm25Encoder};
+pub use config::{CrossEncoderConfig, EmbedderConfig, PretrainedModel};
+#[cfg(feature = "bert")]
+pub use cross_encoder::CrossEncoder;
+#[cfg(not(feature = "bert"))]
+pub use disabled::{BertEmbedder, CrossEncoder};
+pub use error::EmbedderError;
+pub use hashing::HashingEmbedder;
+
+use shared_types::DenseVector;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A sentence embedding backend.
+///
+/// Implementations are blocking (CPU bound); use [`embed`] from async code.
+pub trait Embedder: Send + Sync {
+    /// Embed a batch of texts, returning one vector per input in the same order.
+    fn embed(&self, texts: &[String]) -> Result<Vec<DenseVector>, EmbedderError>;
+
+    /// Dimension of the vectors produced by this embedder.
+    fn dimension(&self) -> usize;
+
+    /// Stable identifier of the underlying model.
+    fn model_id(&self) -> &str;
+}
+
+/// Thread-safe embedder handle shared across tasks.
+pub type SharedEmbedder = Arc<dyn Embedder>;
+
+/// Thread-safe cross-encoder handle shared across tasks.
+pub type SharedCrossEncoder = Arc<CrossEncoder>;
+
+/// Build the embedder selected in configuration
+///
+/// Model loading can take a while (and may download weights), so it runs on
+/// the blocking thread pool.
+pub async fn load(config: &EmbedderConfig) -> Result<SharedEmbedder, EmbedderError> {
+    let config = config.clone();
+
+    let embedder: SharedEmbedder = tokio::task::spawn_blocking(move || match config {
+        EmbedderConfig::RustBert {
+            model,
+            model_dir,
+            model_id,
+        } => BertEmbedder::new(model, model_dir.as_deref().map(Path::new), model_id)
+            .map(|embedder| Arc::new(embedder) as SharedEmbedder),
+        EmbedderConfig::Hashing { dimension } => {
+            HashingEmbedder::new(dimension).map(|embedder| Arc::new(embedder) as SharedEmbedder)
+        }
+    })
+    .await
+    .map_err(|e| EmbedderError::ModelInitError(e.to_string()))??;
+
+    tracing::info!(
+        model_id = embedder.model_id(),
+        dimension = embedder.dimension(),
+        "Embedder initialized"
+    );
+
+    Ok(embedder)
+}
+
+/// Embed a batch of texts without blocking the async runtime
+pub async fn embed(
+    embedder: &SharedEmbedder,
+    texts: Vec<String>,
+) -> Result<Vec<DenseVector>, EmbedderError> {
+    let embedder = embedder.clone();
+
+    tokio::task::spawn_blocking(move || embedder.embed(&texts))
+        .await
+        .map_err(|e| EmbedderError::InferenceError(e.to_string()))?
+}
+
+/// Load the configured cross-encoder on the blocking thread pool
+pub async fn load_cross_encoder(
+    config: &CrossEncoderConfig,
+) -> Result<SharedCrossEncoder, EmbedderError> {
+    let config = config.clone();
+
+    let cross_encoder = tokio::task::spawn_blocking(move || CrossEncoder::new(&config))
+        .await
+        .map_err(|e| EmbedderError::ModelInitError(e.to_string()))??;
+
+    tracing::info!(
+        model_id = cross_encoder.model_id(),
+        "Cross-encoder initialized"
+    );
+
+    Ok(Arc::new(cross_encoder))
+}
+
+/// Score passages against a query without blocking the async runtime
+pub async fn score_passages(
+    cross_encoder: &SharedCrossEncoder,
+    query: String,
+    passages: Vec<String>,
+) -> Result<Vec<f32>, EmbedderError> {
+    let cross_encoder = cross_encoder.clone();
+
+    tokio::task::spawn_blocking(move || cross_encoder.score(&query, &passages))
+        .await
+        .map_err(|e| EmbedderError::InferenceError(e.to_string()))?
+}
+
+/// Fail fast when the embedder's output does not fit the target collection
+pub fn verify_dimension(embedder: &dyn Embedder, expected: usize) -> Result<(), EmbedderError> {
+    if embedder.dimension() == expected {
+        Ok(())
+    } else {
+        Err(EmbedderError::ModelInitError(format!(
+            "Model '{}' produces {}-dimensional vectors but the collection expects {}",
+            embedder.model_id(),
+            embedder.dimension(),
+            expected
+        )))
+    }
+}
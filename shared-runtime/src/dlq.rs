@@ -0,0 +1,114 @@
+use crate::retry::carried_headers;
+use rdkafka::error::KafkaError;
+use rdkafka::message::{Header, Message, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+use std::time::Duration;
+
+/// Route a failed message to the dead-letter topic
+///
+/// The original key and payload are kept byte-for-byte so the message can be
+/// replayed onto the input topic; headers describe where and why it failed,
+/// `stage` being the pipeline stage recorded in `dlq.stage`.
+pub async fn dead_letter<E: From<KafkaError>>(
+    producer: &FutureProducer,
+    topic: &str,
+    send_timeout: Duration,
+    message: &impl Message,
+    stage: &str,
+    error: &str,
+    attempts: u32,
+) -> Result<(), E> {
+    let headers = dead_letter_headers(message, stage, error, attempts);
+    let mut record = FutureRecord::<[u8], [u8]>::to(topic).headers(headers);
+    if let Some(key) = message.key() {
+        record = record.key(key);
+    }
+    if let Some(payload) = message.payload() {
+        record = record.payload(payload);
+    }
+
+    producer
+        .send(record, Timeout::After(send_timeout))
+        .await
+        .map_err(|(e, _)| E::from(e))?;
+
+    tracing::warn!(
+        topic = %topic,
+        stage,
+        original_topic = message.topic(),
+        original_partition = message.partition(),
+        original_offset = message.offset(),
+        "Message routed to dead-letter topic"
+    );
+
+    Ok(())
+}
+
+/// The message's own headers, such as a tombstone's version, plus the
+/// `dlq.*` headers describing the failure
+///
+/// Retry headers are dropped so a replayed message starts its retries afresh.
+fn dead_letter_headers(
+    message: &impl Message,
+    stage: &str,
+    error: &str,
+    attempts: u32,
+) -> OwnedHeaders {
+    let attempts = attempts.to_string();
+    let partition = message.partition().to_string();
+    let offset = message.offset().to_string();
+
+    carried_headers(message, &["dlq.", "retry."])
+        .insert(Header {
+            key: "dlq.stage",
+            value: Some(stage),
+        })
+        .insert(Header {
+            key: "dlq.error",
+            value: Some(error),
+        })
+        .insert(Header {
+            key: "dlq.attempts",
+            value: Some(&attempts),
+        })
+        .insert(Header {
+            key: "dlq.original_topic",
+            value: Some(message.topic()),
+        })
+        .insert(Header {
+            key: "dlq.original_partition",
+            value: Some(&partition),
+        })
+        .insert(Header {
+            key: "dlq.original_offset",
+            value: Some(&offset),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::tests::message;
+    use crate::tombstone;
+    use rdkafka::message::Headers;
+
+    #[test]
+    fn tombstone_version_survives_dead_lettering() {
+        let tombstone = message(tombstone::headers(42).insert(Header {
+            key: "retry.attempt",
+            value: Some("3"),
+        }));
+
+        let dead_lettered = message(dead_letter_headers(&tombstone, "indexing", "boom", 4));
+
+        assert_eq!(tombstone::version(&dead_lettered), Some(42));
+        let headers = dead_lettered.headers().unwrap();
+        assert!(headers.iter().all(|header| header.key != "retry.attempt"));
+        assert!(
+            headers
+                .iter()
+                .any(|header| header.key == "dlq.attempts" && header.value == Some(b"4".as_slice()))
+        );
+    }
+}
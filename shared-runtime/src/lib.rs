@@ -0,0 +1,24 @@
+//! Runtime building blocks shared by the pipeline services.
+//!
+//! Every service drains on SIGTERM / SIGINT through the same [`Shutdown`]
+//! handle. The Kafka workers also share how they retry transient failures
+//! ([`retry`]), route failed messages to their dead-letter topic ([`dlq`]),
+//! report document progress ([`status`]) and version deletions
+//! ([`tombstone`]).
+//!
+//! The Kafka modules sit behind the default `kafka` feature, so the
+//! matching service can use the shutdown handle without librdkafka.
+//! Fallible functions return the caller's own error type, which only has
+//! to convert from [`rdkafka::error::KafkaError`].
+
+#[cfg(feature = "kafka")]
+pub mod dlq;
+#[cfg(feature = "kafka")]
+pub mod retry;
+mod shutdown;
+#[cfg(feature = "kafka")]
+pub mod status;
+#[cfg(feature = "kafka")]
+pub mod tombstone;
+
+pub use shutdown::Shutdown;
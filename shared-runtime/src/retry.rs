@@ -0,0 +1,465 @@
+use crate::Shutdown;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::error::KafkaError;
+use rdkafka::message::{Header, Headers, Message, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::util::Timeout;
+use rdkafka::{Offset, TopicPartitionList};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Header counting how many times a message went through the delayed-retry topic
+const ATTEMPT_HEADER: &str = "retry.attempt";
+/// Header holding the earliest time (Unix epoch milliseconds) a delayed retry may run
+const NOT_BEFORE_HEADER: &str = "retry.not_before";
+/// Delay before the relay tries again to send a message that failed to send
+const RELAY_SEND_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Retry behaviour for transient failures
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetryConfig {
+    /// In-process attempts per message, including the first
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Random spread applied to each delay, as a fraction (0.2 = ±20%)
+    pub jitter: f64,
+    /// Hand messages to a delayed-retry topic once in-process attempts are exhausted
+    #[serde(default)]
+    pub delayed: Option<DelayedRetryConfig>,
+}
+
+/// Retries that outlive a single process, for longer outages
+#[derive(Debug, Deserialize, Clone)]
+pub struct DelayedRetryConfig {
+    pub topic: String,
+    /// Trips through the delayed-retry topic before a message is dead-lettered
+    pub max_attempts: u32,
+    /// Delay before the first delayed retry, doubled on each further trip
+    pub delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 200,
+            max_delay_ms: 5000,
+            jitter: 0.2,
+            delayed: None,
+        }
+    }
+}
+
+/// Errors that may succeed when the same operation is attempted again
+pub trait Retryable {
+    fn is_retryable(&self) -> bool;
+}
+
+/// The last error of an operation and how many times it was attempted
+#[derive(Debug, Clone)]
+pub struct Failure<E> {
+    pub error: E,
+    pub attempts: u32,
+}
+
+impl<E> Failure<E> {
+    /// A failure that was not retried
+    pub fn once(error: E) -> Self {
+        Failure { error, attempts: 1 }
+    }
+}
+
+/// Run `operation` until it succeeds, fails with a non-retryable error, or
+/// `max_attempts` is reached, sleeping with exponential backoff in between
+pub async fn with_backoff<T, E, F, Fut>(
+    policy: &RetryConfig,
+    mut operation: F,
+) -> Result<T, Failure<E>>
+where
+    E: Retryable + std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(error) if error.is_retryable() && attempts < policy.max_attempts => {
+                let delay = backoff_delay(policy, attempts);
+                tracing::warn!(
+                    error = %error,
+                    attempt = attempts,
+                    delay_ms = delay.as_millis() as u64,
+                    "Transient failure, retrying"
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(error) => return Err(Failure { error, attempts }),
+        }
+    }
+}
+
+/// Delay before the attempt following `attempt`: `base * 2^(attempt-1)`,
+/// capped at `max_delay_ms` and spread by ±`jitter`
+fn backoff_delay(policy: &RetryConfig, attempt: u32) -> Duration {
+    let exponential = policy
+        .base_delay_ms
+        .saturating_mul(1u64 << (attempt - 1).min(32))
+        .min(policy.max_delay_ms) as f64;
+    let jitter = policy.jitter.clamp(0.0, 1.0) * (2.0 * random_unit() - 1.0);
+
+    Duration::from_millis((exponential * (1.0 + jitter)).max(0.0) as u64)
+}
+
+/// A uniformly distributed value in `[0, 1)`, good enough for jitter
+fn random_unit() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// How many delayed retries the message has already been through
+pub fn delivery_attempt(message: &impl Message) -> u32 {
+    header_value(message, ATTEMPT_HEADER).unwrap_or(0) as u32
+}
+
+/// Total processing attempts across delayed retries, for the dead-letter header
+///
+/// Every earlier round exhausted the in-process retries before being delayed.
+pub fn total_attempts(policy: &RetryConfig, message: &impl Message, attempts: u32) -> u32 {
+    delivery_attempt(message) * policy.max_attempts + attempts
+}
+
+/// The message's headers, minus those whose key starts with one of `replaced`
+///
+/// Records derived from a message keep its headers, such as a tombstone's
+/// version; the caller adds fresh values for the replaced headers.
+pub(crate) fn carried_headers(message: &impl Message, replaced: &[&str]) -> OwnedHeaders {
+    let Some(headers) = message.headers() else {
+        return OwnedHeaders::new();
+    };
+
+    headers
+        .iter()
+        .filter(|header| !replaced.iter().any(|prefix| header.key.starts_with(prefix)))
+        .fold(OwnedHeaders::new(), |carried, header| {
+            carried.insert(Header {
+                key: header.key,
+                value: header.value,
+            })
+        })
+}
+
+pub(crate) fn header_value(message: &impl Message, key: &str) -> Option<u64> {
+    message
+        .headers()?
+        .iter()
+        .filter(|header| header.key == key)
+        .filter_map(|header| std::str::from_utf8(header.value?).ok()?.parse().ok())
+        .last()
+}
+
+/// Hand a message that failed with a transient error to the delayed-retry topic
+///
+/// Returns `false` when delayed retries are disabled or exhausted, in which
+/// case the caller should dead-letter the message.
+pub async fn schedule_delayed<E: From<KafkaError>>(
+    producer: &FutureProducer,
+    policy: &RetryConfig,
+    send_timeout: Duration,
+    message: &impl Message,
+) -> Result<bool, E> {
+    let Some(delayed) = &policy.delayed else {
+        return Ok(false);
+    };
+    let attempt = delivery_attempt(message);
+    if attempt >= delayed.max_attempts {
+        return Ok(false);
+    }
+
+    let delay_ms = delayed.delay_ms.saturating_mul(1u64 << attempt.min(32));
+    let not_before = now_millis().saturating_add(delay_ms);
+    let headers = delayed_headers(message, attempt + 1, not_before);
+
+    let mut record = FutureRecord::<[u8], [u8]>::to(&delayed.topic).headers(headers);
+    if let Some(key) = message.key() {
+        record = record.key(key);
+    }
+    if let Some(payload) = message.payload() {
+        record = record.payload(payload);
+    }
+
+    producer
+        .send(record, Timeout::After(send_timeout))
+        .await
+        .map_err(|(e, _)| E::from(e))?;
+
+    tracing::warn!(
+        topic = %delayed.topic,
+        attempt = attempt + 1,
+        delay_ms,
+        "Message scheduled for delayed retry"
+    );
+
+    Ok(true)
+}
+
+/// Headers of a message handed to the delayed-retry topic
+fn delayed_headers(message: &impl Message, attempt: u32, not_before: u64) -> OwnedHeaders {
+    carried_headers(message, &["retry."])
+        .insert(Header {
+            key: ATTEMPT_HEADER,
+            value: Some(&attempt.to_string()),
+        })
+        .insert(Header {
+            key: NOT_BEFORE_HEADER,
+            value: Some(&not_before.to_string()),
+        })
+}
+
+/// Headers of a delayed message relayed back to the input topic
+fn relayed_headers(message: &impl Message) -> OwnedHeaders {
+    carried_headers(message, &["retry."]).insert(Header {
+        key: ATTEMPT_HEADER,
+        value: Some(&delivery_attempt(message).to_string()),
+    })
+}
+
+/// Moves messages from the delayed-retry topic back to the input topic once due
+///
+/// The consumer should belong to its own consumer group so waiting for a
+/// delayed message never blocks the main processing loop.
+pub struct Relay {
+    pub consumer: StreamConsumer,
+    pub producer: FutureProducer,
+    pub delayed: DelayedRetryConfig,
+    pub input_topic: String,
+    pub send_timeout: Duration,
+}
+
+/// A partition paused until its first unrelayed message may be relayed
+#[derive(Debug, Clone, Copy)]
+struct Hold {
+    /// Offset the partition was rewound to
+    offset: i64,
+    /// Unix epoch milliseconds at which the partition is resumed
+    until: u64,
+}
+
+impl Relay {
+    /// Relay delayed messages until shutdown
+    ///
+    /// The relay never waits inside the consumer loop. A message that is not
+    /// due yet pauses its partition, which is rewound to that message and
+    /// resumed once it is due, so other partitions keep flowing and the
+    /// consumer keeps polling. Receive, send and commit errors are logged and
+    /// the relay carries on; a message that could not be sent is retried a
+    /// second later. Only failing to subscribe is returned as an error.
+    ///
+    /// A message that was still waiting is not committed and is relayed
+    /// after the restart.
+    pub async fn run<E: From<KafkaError>>(self, shutdown: Shutdown) -> Result<(), E> {
+        self.consumer.subscribe(&[&self.delayed.topic])?;
+
+        tracing::info!(topic = %self.delayed.topic, "Delayed-retry relay started");
+
+        let mut holds: HashMap<(String, i32), Hold> = HashMap::new();
+
+        loop {
+            let next_resume = holds.values().map(|hold| hold.until).min();
+            let resume_due = async {
+                match next_resume {
+                    Some(until) => {
+                        let wait_ms = until.saturating_sub(now_millis());
+                        tokio::time::sleep(Duration::from_millis(wait_ms)).await
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+
+            let received = tokio::select! {
+                biased;
+                _ = shutdown.triggered() => break,
+                _ = resume_due => {
+                    self.resume_due(&mut holds);
+                    continue;
+                }
+                received = self.consumer.recv() => received,
+            };
+            let message = match received {
+                Ok(message) => message,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Kafka receive error in delayed-retry relay");
+                    continue;
+                }
+            };
+
+            let key = (message.topic().to_string(), message.partition());
+            // Fetched before the partition was paused: rewind again so it is
+            // relayed in order once the hold ends
+            if let Some(hold) = holds.get(&key).copied() {
+                self.hold(
+                    &mut holds,
+                    key,
+                    hold.offset.min(message.offset()),
+                    hold.until,
+                );
+                continue;
+            }
+
+            let not_before = header_value(&message, NOT_BEFORE_HEADER).unwrap_or(0);
+            if not_before > now_millis() {
+                self.hold(&mut holds, key, message.offset(), not_before);
+                continue;
+            }
+
+            if let Err(e) = self.forward(&message).await {
+                tracing::warn!(
+                    error = %e,
+                    partition = message.partition(),
+                    offset = message.offset(),
+                    "Failed to relay delayed retry, trying again later"
+                );
+                let until = now_millis().saturating_add(RELAY_SEND_RETRY_DELAY.as_millis() as u64);
+                self.hold(&mut holds, key, message.offset(), until);
+                continue;
+            }
+
+            // A lost commit only relays the message once more after a restart
+            if let Err(e) = self.consumer.commit_message(&message, CommitMode::Async) {
+                tracing::warn!(error = %e, "Failed to commit relayed message");
+            }
+        }
+
+        if let Err(e) = self.producer.flush(Timeout::After(self.send_timeout)) {
+            tracing::warn!(error = %e, "Failed to flush delayed-retry relay producer");
+        }
+        tracing::info!("Delayed-retry relay stopped");
+
+        Ok(())
+    }
+
+    /// Republish a due message to the input topic
+    async fn forward(&self, message: &impl Message) -> Result<(), KafkaError> {
+        let mut record =
+            FutureRecord::<[u8], [u8]>::to(&self.input_topic).headers(relayed_headers(message));
+        if let Some(key) = message.key() {
+            record = record.key(key);
+        }
+        if let Some(payload) = message.payload() {
+            record = record.payload(payload);
+        }
+
+        self.producer
+            .send(record, Timeout::After(self.send_timeout))
+            .await
+            .map(|_| ())
+            .map_err(|(e, _)| e)
+    }
+
+    /// Pause a partition and rewind it to `offset` until `until`
+    fn hold(
+        &self,
+        holds: &mut HashMap<(String, i32), Hold>,
+        (topic, partition): (String, i32),
+        offset: i64,
+        until: u64,
+    ) {
+        let mut partitions = TopicPartitionList::new();
+        partitions.add_partition(&topic, partition);
+        if let Err(e) = self.consumer.pause(&partitions) {
+            tracing::warn!(error = %e, partition, "Failed to pause delayed-retry partition");
+        }
+        // Without the rewind the consumer would skip the held message; it is
+        // uncommitted, so at worst it is relayed after a restart or rebalance
+        if let Err(e) = self.consumer.seek(
+            &topic,
+            partition,
+            Offset::Offset(offset),
+            Timeout::After(self.send_timeout),
+        ) {
+            tracing::error!(
+                error = %e,
+                partition,
+                offset,
+                "Failed to rewind delayed-retry partition"
+            );
+        }
+        holds.insert((topic, partition), Hold { offset, until });
+    }
+
+    /// Resume every held partition whose message is due
+    fn resume_due(&self, holds: &mut HashMap<(String, i32), Hold>) {
+        let now = now_millis();
+        let mut partitions = TopicPartitionList::new();
+        holds.retain(|(topic, partition), hold| {
+            if hold.until > now {
+                return true;
+            }
+            partitions.add_partition(topic, *partition);
+            false
+        });
+        // A partition revoked in the meantime has nothing left to resume
+        if let Err(e) = self.consumer.resume(&partitions) {
+            tracing::warn!(error = %e, "Failed to resume delayed-retry partitions");
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tombstone;
+    use rdkafka::message::{OwnedMessage, Timestamp};
+
+    pub(crate) fn message(headers: OwnedHeaders) -> OwnedMessage {
+        OwnedMessage::new(
+            None,
+            Some(b"3f2c8e4a-0d1b-4e5f-9a6b-7c8d9e0f1a2b".to_vec()),
+            "resume_received".to_string(),
+            Timestamp::NotAvailable,
+            0,
+            7,
+            Some(headers),
+        )
+    }
+
+    #[test]
+    fn tombstone_version_survives_delayed_retry_and_relay() {
+        let tombstone = message(tombstone::headers(42));
+
+        let delayed = message(delayed_headers(&tombstone, 1, 1_000));
+        assert_eq!(tombstone::version(&delayed), Some(42));
+        assert_eq!(delivery_attempt(&delayed), 1);
+        assert_eq!(header_value(&delayed, NOT_BEFORE_HEADER), Some(1_000));
+
+        let relayed = message(relayed_headers(&delayed));
+        assert_eq!(tombstone::version(&relayed), Some(42));
+        assert_eq!(delivery_attempt(&relayed), 1);
+        assert_eq!(header_value(&relayed, NOT_BEFORE_HEADER), None);
+    }
+
+    #[test]
+    fn a_second_delayed_retry_replaces_the_retry_headers() {
+        let first = message(delayed_headers(&message(tombstone::headers(42)), 1, 1_000));
+        let second = message(delayed_headers(&message(relayed_headers(&first)), 2, 5_000));
+
+        let headers = second.headers().unwrap();
+        assert_eq!(headers.count(), 3);
+        assert_eq!(delivery_attempt(&second), 2);
+        assert_eq!(header_value(&second, NOT_BEFORE_HEADER), Some(5_000));
+        assert_eq!(tombstone::version(&second), Some(42));
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Cloneable handle that observes SIGINT / SIGTERM
+///
+/// The first signal flips the handle for every clone, so each part of the
+/// service can stop taking new work independently.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    receiver: watch::Receiver<bool>,
+}
+
+impl Shutdown {
+    /// Start listening for termination signals
+    pub fn listen() -> Self {
+        let (sender, receiver) = watch::channel(false);
+
+        tokio::spawn(async move {
+            signal().await;
+            tracing::info!("Shutdown signal received, draining in-flight work...");
+            let _ = sender.send(true);
+        });
+
+        Self { receiver }
+    }
+
+    /// Whether a termination signal has been received
+    pub fn is_triggered(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// Resolves once a termination signal has been received
+    pub async fn triggered(&self) {
+        let mut receiver = self.receiver.clone();
+        // The sender lives until the signal has been sent, so an error means it already fired
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Resolves `timeout` after a termination signal, bounding the drain
+    pub async fn deadline(&self, timeout: Duration) {
+        self.triggered().await;
+        tokio::time::sleep(timeout).await;
+    }
+}
+
+/// Resolves on the first SIGINT (Ctrl+C) or SIGTERM
+async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "Failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
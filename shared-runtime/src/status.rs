@@ -0,0 +1,47 @@
+use rdkafka::message::Message;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use shared_types::{DocumentStage, DocumentStatusEvent};
+use uuid::Uuid;
+
+/// Report that a document reached a pipeline stage
+///
+/// Status tracking is best-effort: the update is enqueued without waiting
+/// for delivery, and failing to enqueue it never fails the document.
+pub fn publish(producer: &FutureProducer, topic: &str, event: DocumentStatusEvent) {
+    let doc_id = event.id.to_string();
+    let payload = match serde_json::to_vec(&event) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::warn!(%doc_id, error = %e, "Failed to serialize status update");
+            return;
+        }
+    };
+
+    let record = FutureRecord::to(topic).key(&doc_id).payload(&payload);
+    if let Err((e, _)) = producer.send_result(record) {
+        tracing::warn!(%doc_id, error = %e, "Failed to publish status update");
+    }
+}
+
+/// Report that a message was dead-lettered
+///
+/// The document is identified by the message key, so this also covers
+/// messages whose payload could not be deserialized.
+pub fn publish_failed(
+    producer: &FutureProducer,
+    topic: &str,
+    message: &impl Message,
+    reason: String,
+) {
+    let Some(doc_id) = message
+        .key_view::<str>()
+        .and_then(Result::ok)
+        .and_then(|key| Uuid::parse_str(key).ok())
+    else {
+        return;
+    };
+
+    let mut event = DocumentStatusEvent::now(doc_id, DocumentStage::Failed, None);
+    event.reason = Some(reason);
+    publish(producer, topic, event);
+}
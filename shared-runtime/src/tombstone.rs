@@ -0,0 +1,23 @@
+use crate::retry::header_value;
+use rdkafka::message::{Header, Message, OwnedHeaders};
+
+/// Header carrying the version of a deletion tombstone, in decimal
+///
+/// Tombstones have no payload, so the version a deletion applies up to
+/// travels as a header from the ingestion API to the indexing worker.
+pub const VERSION_HEADER: &str = "document.version";
+
+/// Version of a deletion tombstone, if it carries one
+///
+/// Tombstones published before deletions were versioned have none.
+pub fn version(message: &impl Message) -> Option<u64> {
+    header_value(message, VERSION_HEADER)
+}
+
+/// Headers stamping a deletion tombstone with its version
+pub fn headers(version: u64) -> OwnedHeaders {
+    OwnedHeaders::new().insert(Header {
+        key: VERSION_HEADER,
+        value: Some(&version.to_string()),
+    })
+}
@@ -1,7 +1,8 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::types::{DenseVector, DocumentPayload};
+use crate::types::{DenseVector, DocumentMetadata, DocumentPayload, SparseVector};
 
 // ==========================================
 // Event Definitions
@@ -17,11 +18,261 @@ pub struct DocumentReceivedEvent {
 /// Event: Emitted by the Vectorization Worker after successful ML inference.
 /// Consumer: Indexing Worker.
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "VectorizedEventWire")]
 pub struct DocumentVectorizedEvent {
     /// The ID of the original document.
     pub id: Uuid,
-    /// The generated resulting vector embedding.
+    /// One embedding per chunk of the document, in document order.
+    ///
+    /// A document too large for one Kafka message is split over several
+    /// consecutive events, each carrying a run of its chunks.
+    pub chunks: Vec<VectorChunk>,
+    /// Number of chunks of the whole document, across all of its events.
+    pub chunk_count: usize,
+    /// Metadata forwarded from the original document, stored as the Qdrant payload.
+    #[serde(default)]
+    pub metadata: DocumentMetadata,
+    /// Identity of the embedding model that produced the vector.
+    #[serde(default)]
+    pub model_id: String,
+    /// Version of the document the vectors were produced from; 0 if unversioned.
+    #[serde(default)]
+    pub version: u64,
+    /// Fingerprint of the document's normalised content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<ContentFingerprint>,
+    /// An earlier document with the same or nearly the same content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<DuplicateLink>,
+}
+
+/// The model every vector was produced with before events recorded `model_id`.
+const LEGACY_MODEL_ID: &str = "sentence-transformers/all-MiniLM-L6-v2";
+
+/// What a `DocumentVectorizedEvent` is read from.
+///
+/// Events published before documents were chunked carry a single `vector`
+/// for the whole document, and may lack `model_id`; they still sit in the
+/// topic after an upgrade and are read as one chunk.
+#[derive(Deserialize)]
+struct VectorizedEventWire {
+    id: Uuid,
+    #[serde(default)]
+    chunks: Option<Vec<VectorChunk>>,
+    #[serde(default)]
+    chunk_count: Option<usize>,
+    #[serde(default)]
+    vector: Option<DenseVector>,
+    #[serde(default)]
+    metadata: DocumentMetadata,
+    #[serde(default)]
+    model_id: Option<String>,
+    #[serde(default)]
+    version: u64,
+    #[serde(default)]
+    fingerprint: Option<ContentFingerprint>,
+    #[serde(default)]
+    duplicate_of: Option<DuplicateLink>,
+}
+
+impl TryFrom<VectorizedEventWire> for DocumentVectorizedEvent {
+    type Error = String;
+
+    fn try_from(wire: VectorizedEventWire) -> Result<Self, Self::Error> {
+        let chunks = match (wire.chunks, wire.vector) {
+            (Some(chunks), _) => chunks,
+            // The event does not say how long the content was
+            (None, Some(vector)) => vec![VectorChunk {
+                index: 0,
+                start: 0,
+                end: 0,
+                vector,
+                sparse: None,
+                text: None,
+            }],
+            (None, None) => return Err("missing field `chunks`".to_string()),
+        };
+
+        Ok(Self {
+            id: wire.id,
+            // Events published before documents were split carry all chunks
+            chunk_count: wire.chunk_count.unwrap_or(chunks.len()),
+            chunks,
+            metadata: wire.metadata,
+            model_id: wire.model_id.unwrap_or_else(|| LEGACY_MODEL_ID.to_string()),
+            version: wire.version,
+            fingerprint: wire.fingerprint,
+            duplicate_of: wire.duplicate_of,
+        })
+    }
+}
+
+/// Fingerprint of a document's normalised content, used to detect duplicates.
+///
+/// Content is normalised by lowercasing it, dropping punctuation and
+/// collapsing whitespace, so formatting differences do not matter.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ContentFingerprint {
+    /// Hex-encoded SHA-256 of the normalised content; equal for exact duplicates.
+    pub content_hash: String,
+    /// 64-bit SimHash of the normalised content; close for near-duplicates.
+    pub simhash: u64,
+}
+
+/// Link from a document to an earlier one it duplicates.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DuplicateLink {
+    /// The ID of the earlier document.
+    pub id: Uuid,
+    pub kind: DuplicateKind,
+}
+
+/// How closely a document matches the one it duplicates.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKind {
+    /// Identical normalised content.
+    Exact,
+    /// SimHash within the configured distance.
+    Near,
+}
+
+impl DuplicateKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicateKind::Exact => "exact",
+            DuplicateKind::Near => "near",
+        }
+    }
+}
+
+/// The embedding of one contiguous span of a document's content.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VectorChunk {
+    /// Position of the chunk within the document, starting at 0.
+    pub index: usize,
+    /// Byte offset where the chunk starts in the document content.
+    pub start: usize,
+    /// Byte offset just past the end of the chunk in the document content.
+    pub end: usize,
+    /// The embedding of the chunk text.
     pub vector: DenseVector,
+    /// Lexical term weights of the chunk text, for keyword retrieval.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparse: Option<SparseVector>,
+    /// The chunk text, stored with the point so results can be reranked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// How far a document has progressed through the pipeline.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentStage {
+    /// Accepted by the Ingestion API and published to Kafka.
+    #[default]
+    Received,
+    /// Embedded by the Vectorization Worker.
+    Vectorized,
+    /// Stored in Qdrant and searchable.
+    Indexed,
+    /// Routed to a dead-letter topic.
+    Failed,
+    /// Removed from Qdrant.
+    Deleted,
+}
+
+/// Event: Emitted by every service when a document reaches a pipeline stage.
+/// Consumer: Ingestion API, which serves the latest status per document.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentStatusEvent {
+    /// The ID of the document.
+    pub id: Uuid,
+    /// The stage the document reached.
+    pub stage: DocumentStage,
+    /// The document version the stage applies to, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    /// When the stage was reached.
+    pub at: DateTime<Utc>,
+    /// Why processing failed, for the `failed` stage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl DocumentStatusEvent {
+    /// A status event for a stage reached just now.
+    pub fn now(id: Uuid, stage: DocumentStage, version: Option<u64>) -> Self {
+        Self {
+            id,
+            stage,
+            version,
+            at: Utc::now(),
+            reason: None,
+        }
+    }
+}
+
+/// The processing status of a document, as served by the Ingestion API.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DocumentStatus {
+    pub id: Uuid,
+    /// The current stage of the latest known version.
+    pub stage: DocumentStage,
+    /// Whether the document can currently be found by a search.
+    pub searchable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vectorized_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indexed_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Why processing last failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// The response payload of a single document submission.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubmitResponse {
+    /// The ID of the accepted document, assigned by the API if not provided.
+    pub id: Uuid,
+}
+
+/// Outcome of a single document within a batch submission.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    Accepted,
+    Rejected,
+}
+
+/// Per-document result of a batch submission.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchItemResult {
+    /// Position of the document in the submitted array or NDJSON stream.
+    pub index: usize,
+    /// The document ID, if the entry could be parsed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub status: BatchItemStatus,
+    /// Why the document was rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// The response payload of a batch submission, in submission order.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchSubmitResponse {
+    pub accepted: usize,
+    pub rejected: usize,
+    pub results: Vec<BatchItemResult>,
 }
 
 /// Request payload for searching candidates.
@@ -31,6 +282,146 @@ pub struct MatchQuery {
     pub content: String,
     /// How many top results to return (e.g., 10).
     pub top_k: u64,
+    /// How many top results to skip, for paging; defaults to 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    /// Only match documents whose best chunk scores at least this much.
+    ///
+    /// The threshold applies to chunks, so it requires `max` aggregation,
+    /// where a document's score is its best chunk's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_threshold: Option<f32>,
+    /// Optional payload filter restricting which documents can match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<MatchFilter>,
+    /// Overrides the service's default chunk score aggregation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregation: Option<ScoreAggregation>,
+    /// Overrides the service's default retrieval mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retrieval: Option<Retrieval>,
+    /// Turns cross-encoder reranking on or off; defaults to on when configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank: Option<bool>,
+}
+
+/// The request payload for finding documents similar to indexed ones.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SimilarQuery {
+    /// Indexed documents to find more of (at least one).
+    pub positive: Vec<Uuid>,
+    /// Indexed documents the results should be unlike.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub negative: Vec<Uuid>,
+    /// How many top results to return (e.g., 10).
+    pub top_k: u64,
+    /// Optional payload filter restricting which documents can match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<MatchFilter>,
+    /// Overrides the service's default chunk score aggregation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregation: Option<ScoreAggregation>,
+}
+
+/// Which retrievals run for a match query and how their rankings combine.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Retrieval {
+    /// Semantic search over the dense embeddings only.
+    #[default]
+    Dense,
+    /// Keyword search over the sparse BM25 vectors only.
+    Sparse,
+    /// Both searches, fused by reciprocal rank: each ranking adds `1 / (k + rank)`.
+    Rrf {
+        #[serde(default = "default_rrf_k")]
+        k: u32,
+    },
+    /// Both searches, fused by a weighted sum of min-max normalised scores.
+    Weighted {
+        dense_weight: f32,
+        sparse_weight: f32,
+    },
+}
+
+fn default_rrf_k() -> u32 {
+    60
+}
+
+/// How the scores of a document's matching chunks combine into its score.
+///
+/// Documents are selected by their best chunk either way; the other
+/// strategies only reorder the documents that selection returned.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum ScoreAggregation {
+    /// Score of the best-matching chunk.
+    #[default]
+    Max,
+    /// Mean score of the `n` best-matching chunks.
+    MeanTopN { n: u32 },
+    /// Sum of the scores of the document's best-matching chunks, at most
+    /// `chunks_per_document` of them.
+    Sum,
+}
+
+/// Boolean combination of payload conditions applied to a match query.
+///
+/// Every `must` condition has to hold, at least one `should` condition
+/// (when any are given), and none of the `must_not` conditions.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MatchFilter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub must: Vec<FilterCondition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub should: Vec<FilterCondition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub must_not: Vec<FilterCondition>,
+}
+
+/// A single condition on a payload field.
+///
+/// Keys address the stored metadata, e.g. `tags`, `source`, `created_at`
+/// or `attributes.years_experience`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterCondition {
+    /// The field equals a keyword, integer or boolean value.
+    Equals {
+        key: String,
+        value: serde_json::Value,
+    },
+    /// The field equals any of the given keywords or integers.
+    AnyOf {
+        key: String,
+        values: Vec<serde_json::Value>,
+    },
+    /// The numeric field lies within the given bounds.
+    Range {
+        key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gt: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gte: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lt: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lte: Option<f64>,
+    },
+    /// The date field lies within the given RFC 3339 bounds.
+    DatetimeRange {
+        key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gt: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gte: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lt: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lte: Option<String>,
+    },
+    /// A nested must/should/must_not group.
+    Filter(MatchFilter),
 }
 
 /// A single matched candidate result.
@@ -38,12 +429,162 @@ pub struct MatchQuery {
 pub struct MatchResult {
     /// The candidate document ID.
     pub id: Uuid,
-    /// The similarity score (higher is better for Cosine).
+    /// The similarity score (higher is better for Cosine), or the rerank
+    /// score when the result was reranked.
     pub score: f32,
+    /// The score from the retrieval stage, when the result was reranked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retrieval_score: Option<f32>,
+    /// The cross-encoder relevance in (0, 1), when the result was reranked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank_score: Option<f32>,
+    /// The best-matching chunks of the document, best first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<MatchedChunk>,
+}
+
+/// A span of a matched document and its similarity to the query.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MatchedChunk {
+    /// Position of the chunk within the document.
+    pub index: usize,
+    /// Byte offset where the chunk starts in the document content.
+    pub start: usize,
+    /// Byte offset just past the end of the chunk in the document content.
+    pub end: usize,
+    /// The similarity score of this chunk.
+    pub score: f32,
+    /// The chunk text, loaded for reranking and left out of responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
 }
 
 /// The response payload containing ranked matches.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MatchResponse {
     pub matches: Vec<MatchResult>,
+    /// The `offset` of the next page, when there are more results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<u64>,
+    /// How many documents clear the query's `score_threshold`, counted up to
+    /// a service limit, when the query has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_above_threshold: Option<u64>,
+}
+
+/// Per-query result of a batch match.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchMatchResult {
+    /// Position of the query in the submitted `queries` array.
+    pub index: usize,
+    /// The HTTP status the query would have received on its own.
+    pub status: u16,
+    /// The matches, when the query succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<MatchResponse>,
+    /// Why the query failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The response payload of a batch match, in query order.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchMatchResponse {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchMatchResult>,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_chunked_events() {
+        let event: DocumentVectorizedEvent = serde_json::from_value(json!({
+            "id": Uuid::nil(),
+            "chunks": [
+                { "index": 0, "start": 0, "end": 5, "vector": [0.1, 0.2] },
+                { "index": 1, "start": 4, "end": 9, "vector": [0.3, 0.4] }
+            ],
+            "model_id": "hashing-2",
+            "version": 7
+        }))
+        .unwrap();
+
+        assert_eq!(event.chunks.len(), 2);
+        assert_eq!(event.chunk_count, 2);
+        assert_eq!(event.chunks[1].end, 9);
+        assert_eq!(event.model_id, "hashing-2");
+        assert_eq!(event.version, 7);
+    }
+
+    #[test]
+    fn reads_one_part_of_a_split_event() {
+        let event: DocumentVectorizedEvent = serde_json::from_value(json!({
+            "id": Uuid::nil(),
+            "chunks": [{ "index": 3, "start": 30, "end": 40, "vector": [0.1] }],
+            "chunk_count": 5,
+            "model_id": "hashing-1",
+            "version": 7
+        }))
+        .unwrap();
+
+        assert_eq!(event.chunks[0].index, 3);
+        assert_eq!(event.chunk_count, 5);
+
+        let round_trip: DocumentVectorizedEvent =
+            serde_json::from_slice(&serde_json::to_vec(&event).unwrap()).unwrap();
+        assert_eq!(round_trip.chunk_count, 5);
+    }
+
+    #[test]
+    fn reads_a_legacy_vector_as_one_chunk() {
+        let event: DocumentVectorizedEvent = serde_json::from_value(json!({
+            "id": Uuid::nil(),
+            "vector": [0.1, 0.2, 0.3]
+        }))
+        .unwrap();
+
+        assert_eq!(event.chunks.len(), 1);
+        assert_eq!(event.chunks[0].index, 0);
+        assert_eq!(event.chunks[0].vector, vec![0.1, 0.2, 0.3]);
+        assert_eq!(event.model_id, LEGACY_MODEL_ID);
+        assert_eq!(event.version, 0);
+    }
+
+    #[test]
+    fn keeps_the_model_of_a_legacy_event_that_names_it() {
+        let event: DocumentVectorizedEvent = serde_json::from_value(json!({
+            "id": Uuid::nil(),
+            "vector": [0.1],
+            "model_id": "local:resume-minilm"
+        }))
+        .unwrap();
+
+        assert_eq!(event.model_id, "local:resume-minilm");
+    }
+
+    #[test]
+    fn rejects_events_without_vectors() {
+        let result = serde_json::from_value::<DocumentVectorizedEvent>(json!({ "id": Uuid::nil() }));
+
+        assert!(result.unwrap_err().to_string().contains("chunks"));
+    }
+
+    #[test]
+    fn round_trips_through_the_chunked_form() {
+        let event: DocumentVectorizedEvent = serde_json::from_value(json!({
+            "id": Uuid::nil(),
+            "vector": [0.5]
+        }))
+        .unwrap();
+        let encoded = serde_json::to_value(&event).unwrap();
+
+        assert!(encoded.get("vector").is_none());
+        let decoded: DocumentVectorizedEvent = serde_json::from_value(encoded).unwrap();
+        assert_eq!(decoded.chunks[0].vector, vec![0.5]);
+    }
 }
\ No newline at end of file
@@ -7,5 +7,14 @@ mod events;
 mod types;
 
 // Re-export public types
-pub use events::{DocumentReceivedEvent, DocumentVectorizedEvent, MatchQuery, MatchResponse, MatchResult};
-pub use types::{DenseVector, DocumentPayload};
+pub use events::{
+    BatchItemResult, BatchItemStatus, BatchMatchResponse, BatchMatchResult, BatchSubmitResponse,
+    ContentFingerprint, DocumentReceivedEvent, DocumentStage, DocumentStatus, DocumentStatusEvent,
+    DocumentVectorizedEvent, DuplicateKind, DuplicateLink, FilterCondition, MatchFilter,
+    MatchQuery, MatchResponse, MatchResult, MatchedChunk, Retrieval, ScoreAggregation,
+    SimilarQuery, SubmitResponse, VectorChunk,
+};
+pub use types::{
+    DenseVector, DocumentKind, DocumentMetadata, DocumentPayload, DocumentSubmission,
+    MAX_DOCUMENT_VERSION, SparseVector,
+};
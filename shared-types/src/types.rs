@@ -1,10 +1,28 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 /// Type alias for a dense vector embedding.
 /// Standard transformer models (like BERT-base) typically output 768 dimensions.
 pub type DenseVector = Vec<f32>;
 
+/// A sparse vector, e.g. lexical term weights over a hashed vocabulary.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SparseVector {
+    /// Dimensions with a non-zero weight, in ascending order.
+    pub indices: Vec<u32>,
+    /// Weight of each dimension listed in `indices`.
+    pub values: Vec<f32>,
+}
+
+impl SparseVector {
+    /// Whether no dimension has a weight.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+}
+
 /// The core entity representing a resume or job description.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DocumentPayload {
@@ -12,5 +30,80 @@ pub struct DocumentPayload {
     pub id: Uuid,
     /// The raw text content to be embedded.
     pub content: String,
-    // Future extensibility: Add metadata fields here (e.g., source, timestamp).
+    /// Structured metadata carried through the pipeline into the Qdrant payload.
+    #[serde(default)]
+    pub metadata: DocumentMetadata,
+    /// Monotonically increasing version of the document, at most
+    /// [`MAX_DOCUMENT_VERSION`]. Stamped with the ingestion time in
+    /// microseconds when the caller does not provide one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+/// Largest document version: the index stores versions as signed 64-bit integers.
+pub const MAX_DOCUMENT_VERSION: u64 = i64::MAX as u64;
+
+/// A document as submitted to the Ingestion API.
+///
+/// Same as [`DocumentPayload`], except that the ID may be left out for the
+/// API to assign.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentSubmission {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub content: String,
+    #[serde(default)]
+    pub metadata: DocumentMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+impl DocumentSubmission {
+    /// The document to publish, identified by `id`.
+    pub fn into_payload(self, id: Uuid) -> DocumentPayload {
+        DocumentPayload {
+            id,
+            content: self.content,
+            metadata: self.metadata,
+            version: self.version,
+        }
+    }
+}
+
+/// The kind of document being ingested.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    Resume,
+    JobDescription,
+    Other,
+}
+
+/// Typed metadata attached to a document.
+///
+/// Stored as the Qdrant point payload, so every field here can be used
+/// to filter search results downstream.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DocumentMetadata {
+    /// Where the document came from (e.g., a job board or upload channel).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// What kind of document this is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<DocumentKind>,
+    /// When the document was originally created (RFC 3339).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Name of the file the content was extracted from, for uploaded documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    /// MIME type of the uploaded file the content was extracted from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Free-form labels (e.g., "backend", "remote").
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Arbitrary key/value attributes (e.g., "years_experience": 7).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, serde_json::Value>,
 }
@@ -0,0 +1,294 @@
+use crate::config::ChunkingConfig;
+use crate::error::WorkerError;
+
+/// A contiguous span of a document's content, as byte offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunk {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Reject chunking settings that would loop forever or produce empty chunks
+pub fn validate(config: &ChunkingConfig) -> Result<(), WorkerError> {
+    match config {
+        ChunkingConfig::None => Ok(()),
+        ChunkingConfig::SlidingWindow {
+            max_tokens,
+            overlap_tokens,
+        }
+        | ChunkingConfig::Paragraph {
+            max_tokens,
+            overlap_tokens,
+        } => {
+            if *max_tokens == 0 {
+                Err(WorkerError::InvalidConfig(
+                    "chunking.max_tokens must be greater than 0".to_string(),
+                ))
+            } else if overlap_tokens >= max_tokens {
+                Err(WorkerError::InvalidConfig(format!(
+                    "chunking.overlap_tokens ({}) must be smaller than max_tokens ({})",
+                    overlap_tokens, max_tokens
+                )))
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Split document content into the spans that are embedded individually
+///
+/// Always returns at least one chunk so every document is indexed, even if
+/// its content is empty. Tokens are whitespace-separated words; the model's
+/// tokenizer usually splits words further, so `max_tokens` should stay well
+/// below the model's maximum sequence length.
+pub fn split(content: &str, config: &ChunkingConfig) -> Vec<Chunk> {
+    let words = word_spans(content);
+    if words.is_empty() {
+        return vec![Chunk {
+            start: 0,
+            end: content.len(),
+        }];
+    }
+
+    match config {
+        ChunkingConfig::None => vec![Chunk {
+            start: 0,
+            end: content.len(),
+        }],
+        ChunkingConfig::SlidingWindow {
+            max_tokens,
+            overlap_tokens,
+        } => sliding_windows(&words, *max_tokens, *overlap_tokens),
+        ChunkingConfig::Paragraph {
+            max_tokens,
+            overlap_tokens,
+        } => paragraphs(content, &words, *max_tokens, *overlap_tokens),
+    }
+}
+
+/// Fixed-size word windows, each sharing `overlap` words with the previous one
+fn sliding_windows(words: &[Chunk], max_tokens: usize, overlap: usize) -> Vec<Chunk> {
+    let step = max_tokens.saturating_sub(overlap).max(1);
+    let mut chunks = Vec::new();
+    let mut first = 0;
+
+    loop {
+        let last = (first + max_tokens).min(words.len()) - 1;
+        chunks.push(Chunk {
+            start: words[first].start,
+            end: words[last].end,
+        });
+        if last + 1 >= words.len() {
+            break;
+        }
+        first += step;
+    }
+
+    chunks
+}
+
+/// Paragraphs (separated by blank lines) packed greedily up to `max_tokens`
+///
+/// Paragraphs longer than `max_tokens` fall back to sliding windows.
+fn paragraphs(content: &str, words: &[Chunk], max_tokens: usize, overlap: usize) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut current: Option<(Chunk, usize)> = None;
+
+    for paragraph in paragraph_spans(content) {
+        let paragraph_words: Vec<Chunk> = words
+            .iter()
+            .copied()
+            .filter(|w| w.start >= paragraph.start && w.end <= paragraph.end)
+            .collect();
+        if paragraph_words.is_empty() {
+            continue;
+        }
+
+        if paragraph_words.len() > max_tokens {
+            chunks.extend(current.take().map(|(chunk, _)| chunk));
+            chunks.extend(sliding_windows(&paragraph_words, max_tokens, overlap));
+            continue;
+        }
+
+        current = match current.take() {
+            Some((chunk, count)) if count + paragraph_words.len() <= max_tokens => Some((
+                Chunk {
+                    start: chunk.start,
+                    end: paragraph.end,
+                },
+                count + paragraph_words.len(),
+            )),
+            previous => {
+                chunks.extend(previous.map(|(chunk, _)| chunk));
+                Some((paragraph, paragraph_words.len()))
+            }
+        };
+    }
+
+    chunks.extend(current.map(|(chunk, _)| chunk));
+    chunks
+}
+
+/// Byte spans of whitespace-separated words
+fn word_spans(content: &str) -> Vec<Chunk> {
+    let mut spans = Vec::new();
+    let mut start = None;
+
+    for (offset, c) in content.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                spans.push(Chunk {
+                    start: s,
+                    end: offset,
+                });
+                start = None;
+            }
+            (false, None) => start = Some(offset),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push(Chunk {
+            start: s,
+            end: content.len(),
+        });
+    }
+
+    spans
+}
+
+/// Byte spans of paragraphs, trimmed of surrounding whitespace
+fn paragraph_spans(content: &str) -> Vec<Chunk> {
+    let mut spans = Vec::new();
+    let mut current: Option<Chunk> = None;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            spans.extend(current.take());
+        } else {
+            let start = offset + (line.len() - line.trim_start().len());
+            let end = start + trimmed.len();
+            current = Some(match current {
+                Some(paragraph) => Chunk {
+                    start: paragraph.start,
+                    end,
+                },
+                None => Chunk { start, end },
+            });
+        }
+        offset += line.len();
+    }
+    spans.extend(current);
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sliding(max_tokens: usize, overlap_tokens: usize) -> ChunkingConfig {
+        ChunkingConfig::SlidingWindow {
+            max_tokens,
+            overlap_tokens,
+        }
+    }
+
+    fn paragraph(max_tokens: usize, overlap_tokens: usize) -> ChunkingConfig {
+        ChunkingConfig::Paragraph {
+            max_tokens,
+            overlap_tokens,
+        }
+    }
+
+    fn texts<'a>(content: &'a str, config: &ChunkingConfig) -> Vec<&'a str> {
+        split(content, config)
+            .into_iter()
+            .map(|chunk| &content[chunk.start..chunk.end])
+            .collect()
+    }
+
+    #[test]
+    fn validate_rejects_settings_that_cannot_progress() {
+        assert!(validate(&sliding(0, 0)).is_err());
+        assert!(validate(&sliding(10, 10)).is_err());
+        assert!(validate(&paragraph(10, 12)).is_err());
+        assert!(validate(&sliding(10, 9)).is_ok());
+        assert!(validate(&ChunkingConfig::None).is_ok());
+    }
+
+    #[test]
+    fn empty_content_still_yields_one_chunk() {
+        assert_eq!(split("", &sliding(3, 1)), vec![Chunk { start: 0, end: 0 }]);
+        assert_eq!(
+            split("  \n ", &sliding(3, 1)),
+            vec![Chunk { start: 0, end: 4 }]
+        );
+    }
+
+    #[test]
+    fn no_chunking_keeps_the_whole_content() {
+        assert_eq!(
+            texts(" one two three ", &ChunkingConfig::None),
+            vec![" one two three "]
+        );
+    }
+
+    #[test]
+    fn sliding_windows_overlap() {
+        assert_eq!(texts("a b c d e", &sliding(3, 1)), vec!["a b c", "c d e"]);
+        assert_eq!(
+            texts("a b c d e f", &sliding(3, 1)),
+            vec!["a b c", "c d e", "e f"]
+        );
+    }
+
+    #[test]
+    fn short_content_is_a_single_window() {
+        assert_eq!(texts("just two", &sliding(150, 30)), vec!["just two"]);
+    }
+
+    #[test]
+    fn offsets_respect_multibyte_characters() {
+        let content = "café naïve résumé";
+
+        assert_eq!(texts(content, &sliding(2, 0)), vec!["café naïve", "résumé"]);
+    }
+
+    #[test]
+    fn paragraphs_are_packed_up_to_the_limit() {
+        let content = "one two\n\nthree four\n\nfive six seven eight";
+
+        assert_eq!(
+            texts(content, &paragraph(4, 1)),
+            vec!["one two\n\nthree four", "five six seven eight"]
+        );
+    }
+
+    #[test]
+    fn long_paragraphs_fall_back_to_windows() {
+        let content = "intro\n\na b c\n\noutro";
+
+        assert_eq!(
+            texts(content, &paragraph(2, 1)),
+            vec!["intro", "a b", "b c", "outro"]
+        );
+    }
+
+    #[test]
+    fn paragraph_spans_are_trimmed() {
+        let content = "  first line\n  second line  \n\n\n third \n";
+
+        assert_eq!(
+            texts(content, &paragraph(100, 0)),
+            vec!["first line\n  second line  \n\n\n third"]
+        );
+        assert_eq!(
+            texts(content, &paragraph(4, 0)),
+            vec!["first line\n  second line", "third"]
+        );
+    }
+}
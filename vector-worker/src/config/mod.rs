@@ -1,4 +1,6 @@
 use serde::Deserialize;
+use shared_embedder::{Bm25Config, EmbedderConfig};
+use shared_runtime::retry::RetryConfig;
 use std::fs;
 
 /// Configuration for the vector worker
@@ -7,9 +9,87 @@ pub struct Config {
     pub kafka_broker: String,
     pub input_topic: String,
     pub output_topic: String,
+    pub dead_letter_topic: String,
+    pub status_topic: String,
     pub consumer_group: String,
     pub kafka_timeout_ms: String,
     pub kafka_send_timeout_secs: u64,
+    /// Largest message the producer sends; vectorized events above it are
+    /// split. Must not exceed the broker's `message.max.bytes`.
+    pub message_max_bytes: usize,
+    pub embedder: EmbedderConfig,
+    pub vector_dimension: usize,
+    pub batch_size: usize,
+    pub batch_linger_ms: u64,
+    pub chunking: ChunkingConfig,
+    /// BM25 sparse vectors for keyword retrieval; without it only dense vectors are produced
+    #[serde(default)]
+    pub sparse: Option<Bm25Config>,
+    pub dedup: DedupConfig,
+    pub retry: RetryConfig,
+    pub shutdown_timeout_secs: u64,
+}
+
+/// How document content is split before embedding
+///
+/// Token counts are whitespace-separated words.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum ChunkingConfig {
+    /// Embed the whole content as one input (truncated by the model)
+    None,
+    /// Fixed-size windows that overlap by `overlap_tokens`
+    SlidingWindow {
+        max_tokens: usize,
+        overlap_tokens: usize,
+    },
+    /// Paragraphs packed up to `max_tokens`; longer paragraphs are windowed
+    Paragraph {
+        max_tokens: usize,
+        overlap_tokens: usize,
+    },
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        ChunkingConfig::SlidingWindow {
+            max_tokens: 150,
+            overlap_tokens: 30,
+        }
+    }
+}
+
+/// Detection of documents that duplicate an earlier one
+#[derive(Debug, Deserialize, Clone)]
+pub struct DedupConfig {
+    pub mode: DedupMode,
+    /// Largest SimHash distance (differing bits out of 64) of a near-duplicate;
+    /// 0 detects exact duplicates only
+    pub near_duplicate_distance: u32,
+    /// Recently vectorized documents remembered for duplicate detection
+    pub cache_size: usize,
+}
+
+/// What happens to a duplicate document
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupMode {
+    /// Every document is embedded and indexed independently
+    Off,
+    /// Exact duplicates reuse the vectors of the earlier document instead of being embedded
+    ReuseVectors,
+    /// Duplicates are embedded as usual and linked to the earlier document in the payload
+    Link,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            mode: DedupMode::Off,
+            near_duplicate_distance: 3,
+            cache_size: 10_000,
+        }
+    }
 }
 
 impl Config {
@@ -26,9 +106,21 @@ impl Config {
             kafka_broker: "localhost:9092".to_string(),
             input_topic: "resume_received".to_string(),
             output_topic: "resume_vectorized".to_string(),
+            dead_letter_topic: "resume_received_dlq".to_string(),
+            status_topic: "document_status".to_string(),
             consumer_group: "vectorizer_group_v1".to_string(),
             kafka_timeout_ms: "5000".to_string(),
             kafka_send_timeout_secs: 5,
+            message_max_bytes: 1_000_000,
+            embedder: EmbedderConfig::default(),
+            vector_dimension: 384,
+            batch_size: 32,
+            batch_linger_ms: 50,
+            chunking: ChunkingConfig::default(),
+            sparse: None,
+            dedup: DedupConfig::default(),
+            retry: RetryConfig::default(),
+            shutdown_timeout_secs: 30,
         }
     }
 }
@@ -0,0 +1,388 @@
+use crate::config::{DedupConfig, DedupMode};
+use sha2::{Digest, Sha256};
+use shared_types::{ContentFingerprint, DenseVector, DuplicateKind, DuplicateLink};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Words per shingle fed into the SimHash
+const SHINGLE_WORDS: usize = 3;
+
+/// Fingerprint the normalised content of a document
+///
+/// Normalisation lowercases words, drops everything but letters and digits
+/// and collapses whitespace, so the same resume exported by different job
+/// boards hashes the same.
+pub fn fingerprint(content: &str) -> ContentFingerprint {
+    let words = normalize(content);
+
+    let digest = Sha256::digest(words.join(" ").as_bytes());
+    let content_hash = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    ContentFingerprint {
+        content_hash,
+        simhash: simhash(&words),
+    }
+}
+
+fn normalize(content: &str) -> Vec<String> {
+    content
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// 64-bit SimHash over overlapping word shingles
+///
+/// Documents differing in a few words share most shingles, so their hashes
+/// differ in only a few bits.
+fn simhash(words: &[String]) -> u64 {
+    let mut weights = [0i64; 64];
+    for shingle in words.windows(SHINGLE_WORDS.min(words.len()).max(1)) {
+        let hash = fnv1a(&shingle.join(" "));
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash >> bit & 1 == 1 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |hash, (bit, _)| hash | 1 << bit)
+}
+
+/// FNV-1a, a hash that is stable across processes and releases
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// A recently vectorized document
+struct Remembered {
+    id: Uuid,
+    simhash: u64,
+    /// Chunk vectors, kept only when they may be reused
+    vectors: Option<Vec<DenseVector>>,
+    /// Insertion sequence, the document's position in the eviction order
+    sequence: u64,
+}
+
+/// Bounded memory of recently vectorized documents, keyed by content hash
+///
+/// Lives in the worker process: duplicates are only detected against
+/// documents this instance vectorized since it started, oldest forgotten
+/// first.
+///
+/// Documents are also indexed by ID, and by each band of their SimHash:
+/// split into `near_duplicate_distance + 1` bands, two hashes within that
+/// distance agree on at least one band, so near-duplicate candidates are
+/// found without scanning the whole cache.
+pub struct DedupCache {
+    config: DedupConfig,
+    entries: HashMap<String, Remembered>,
+    /// Content hash of each remembered document
+    hashes: HashMap<Uuid, String>,
+    /// Content hashes sharing a SimHash band, keyed by band number and value
+    bands: HashMap<(u32, u64), HashSet<String>>,
+    /// Content hashes by insertion sequence, oldest first
+    order: BTreeMap<u64, String>,
+    next_sequence: u64,
+}
+
+impl DedupCache {
+    pub fn new(config: &DedupConfig) -> Self {
+        Self {
+            config: config.clone(),
+            entries: HashMap::new(),
+            hashes: HashMap::new(),
+            bands: HashMap::new(),
+            order: BTreeMap::new(),
+            next_sequence: 0,
+        }
+    }
+
+    /// An earlier document duplicating `fingerprint`, other than `id` itself
+    ///
+    /// Exact duplicates are preferred; near-duplicates are the closest
+    /// document within `near_duplicate_distance`.
+    pub fn find(&self, id: Uuid, fingerprint: &ContentFingerprint) -> Option<DuplicateLink> {
+        if let Some(entry) = self.entries.get(&fingerprint.content_hash)
+            && entry.id != id
+        {
+            return Some(DuplicateLink {
+                id: entry.id,
+                kind: DuplicateKind::Exact,
+            });
+        }
+
+        if self.config.near_duplicate_distance == 0 {
+            return None;
+        }
+        self.band_keys(fingerprint.simhash)
+            .filter_map(|key| self.bands.get(&key))
+            .flatten()
+            .filter_map(|hash| self.entries.get(hash))
+            .filter(|entry| entry.id != id)
+            .map(|entry| (entry, (entry.simhash ^ fingerprint.simhash).count_ones()))
+            .filter(|(_, distance)| *distance <= self.config.near_duplicate_distance)
+            .min_by_key(|(entry, distance)| (*distance, entry.sequence))
+            .map(|(entry, _)| DuplicateLink {
+                id: entry.id,
+                kind: DuplicateKind::Near,
+            })
+    }
+
+    /// Vectors of an exact duplicate that can stand in for embedding `fingerprint`
+    pub fn reusable_vectors(
+        &self,
+        id: Uuid,
+        fingerprint: &ContentFingerprint,
+    ) -> Option<&[DenseVector]> {
+        if self.config.mode != DedupMode::ReuseVectors {
+            return None;
+        }
+
+        self.entries
+            .get(&fingerprint.content_hash)
+            .filter(|entry| entry.id != id)
+            .and_then(|entry| entry.vectors.as_deref())
+    }
+
+    /// Remember a vectorized document, evicting the oldest beyond `cache_size`
+    pub fn remember(
+        &mut self,
+        id: Uuid,
+        fingerprint: &ContentFingerprint,
+        vectors: &[DenseVector],
+    ) {
+        if self.config.mode == DedupMode::Off || self.config.cache_size == 0 {
+            return;
+        }
+
+        // A re-submitted document no longer has its previous content, and
+        // the latest document with this content replaces the earlier one
+        self.forget(id);
+        self.remove(&fingerprint.content_hash);
+
+        let hash = fingerprint.content_hash.clone();
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        let vectors = (self.config.mode == DedupMode::ReuseVectors).then(|| vectors.to_vec());
+
+        for key in self.band_keys(fingerprint.simhash).collect::<Vec<_>>() {
+            self.bands.entry(key).or_default().insert(hash.clone());
+        }
+        self.hashes.insert(id, hash.clone());
+        self.order.insert(sequence, hash.clone());
+        self.entries.insert(
+            hash,
+            Remembered {
+                id,
+                simhash: fingerprint.simhash,
+                vectors,
+                sequence,
+            },
+        );
+
+        while self.entries.len() > self.config.cache_size {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+
+    /// Forget a deleted document so nothing is linked to it any more
+    pub fn forget(&mut self, id: Uuid) {
+        if let Some(hash) = self.hashes.remove(&id) {
+            self.remove(&hash);
+        }
+    }
+
+    /// Drop the document remembered under `hash` from every index
+    fn remove(&mut self, hash: &str) {
+        let Some(entry) = self.entries.remove(hash) else {
+            return;
+        };
+        self.hashes.remove(&entry.id);
+        self.order.remove(&entry.sequence);
+        for key in self.band_keys(entry.simhash).collect::<Vec<_>>() {
+            if let Some(hashes) = self.bands.get_mut(&key) {
+                hashes.remove(hash);
+                if hashes.is_empty() {
+                    self.bands.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Band number and value of each SimHash band; none without near-duplicate detection
+    fn band_keys(&self, simhash: u64) -> impl Iterator<Item = (u32, u64)> + use<> {
+        let distance = self.config.near_duplicate_distance;
+        let count = if distance == 0 {
+            0
+        } else {
+            (distance + 1).min(64)
+        };
+        let width = if count == 0 {
+            64
+        } else {
+            64_u32.div_ceil(count)
+        };
+
+        (0..count)
+            .take_while(move |band| band * width < 64)
+            .map(move |band| {
+                let value = (simhash >> (band * width)) & (u64::MAX >> (64 - width));
+                (band, value)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESUME: &str = "Senior software engineer with ten years of experience building \
+        distributed systems in Rust and Go. Led the migration of a payment platform to \
+        event sourcing on Kafka, mentored five engineers and ran the on-call rotation.";
+
+    fn distance(a: &str, b: &str) -> u32 {
+        (fingerprint(a).simhash ^ fingerprint(b).simhash).count_ones()
+    }
+
+    #[test]
+    fn formatting_does_not_change_the_content_hash() {
+        let reformatted = RESUME.to_uppercase().replace(' ', "  \n").replace('.', ";");
+
+        assert_eq!(
+            fingerprint(RESUME).content_hash,
+            fingerprint(&reformatted).content_hash
+        );
+        assert_eq!(distance(RESUME, &reformatted), 0);
+    }
+
+    #[test]
+    fn content_hash_is_hex_sha256() {
+        let hash = fingerprint("hello world").content_hash;
+
+        assert_eq!(hash.len(), 64);
+        assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(hash, fingerprint("hello there").content_hash);
+    }
+
+    #[test]
+    fn small_edits_move_the_simhash_less_than_other_documents() {
+        let edited = RESUME.replace("five", "six");
+        let unrelated = "Registered nurse with pediatric intensive care experience, \
+            certified in advanced life support and fluent in Spanish and Portuguese.";
+
+        assert_ne!(
+            fingerprint(RESUME).content_hash,
+            fingerprint(&edited).content_hash
+        );
+        assert!(distance(RESUME, &edited) < distance(RESUME, unrelated));
+        assert!(distance(RESUME, &edited) <= 12);
+    }
+
+    fn cache(mode: DedupMode, cache_size: usize) -> DedupCache {
+        DedupCache::new(&DedupConfig {
+            mode,
+            near_duplicate_distance: 3,
+            cache_size,
+        })
+    }
+
+    fn with_simhash(content_hash: &str, simhash: u64) -> ContentFingerprint {
+        ContentFingerprint {
+            content_hash: content_hash.to_string(),
+            simhash,
+        }
+    }
+
+    #[test]
+    fn exact_and_near_duplicates_are_found() {
+        let mut cache = cache(DedupMode::Link, 10);
+        let original = Uuid::new_v4();
+        cache.remember(original, &with_simhash("a", 0xffff_0000_ffff_0000), &[]);
+
+        let exact = cache.find(Uuid::new_v4(), &with_simhash("a", 0));
+        assert_eq!(
+            exact.map(|link| (link.id, link.kind)),
+            Some((original, DuplicateKind::Exact))
+        );
+
+        // Three differing bits, spread over three of the four bands
+        let near = cache.find(Uuid::new_v4(), &with_simhash("b", 0xfffe_0001_fffe_0000));
+        assert_eq!(
+            near.map(|link| (link.id, link.kind)),
+            Some((original, DuplicateKind::Near))
+        );
+
+        let far = cache.find(Uuid::new_v4(), &with_simhash("c", 0xfffe_0001_fffe_0001));
+        assert!(far.is_none());
+        assert!(cache.find(original, &with_simhash("a", 0)).is_none());
+    }
+
+    #[test]
+    fn forgotten_and_resubmitted_documents_no_longer_match() {
+        let mut cache = cache(DedupMode::Link, 10);
+        let id = Uuid::new_v4();
+        cache.remember(id, &with_simhash("a", 1), &[]);
+        cache.remember(id, &with_simhash("b", u64::MAX), &[]);
+
+        assert!(cache.find(Uuid::new_v4(), &with_simhash("a", 1)).is_none());
+        assert!(cache.find(Uuid::new_v4(), &with_simhash("b", 0)).is_some());
+
+        cache.forget(id);
+        assert!(
+            cache
+                .find(Uuid::new_v4(), &with_simhash("b", u64::MAX))
+                .is_none()
+        );
+        assert!(cache.bands.is_empty() && cache.hashes.is_empty() && cache.order.is_empty());
+    }
+
+    #[test]
+    fn oldest_document_is_evicted() {
+        let mut cache = cache(DedupMode::ReuseVectors, 2);
+        let vectors = vec![vec![1.0, 0.0]];
+        for (hash, simhash) in [("a", 0), ("b", 0xffff), ("c", 0xffff_0000)] {
+            cache.remember(Uuid::new_v4(), &with_simhash(hash, simhash), &vectors);
+        }
+
+        assert!(
+            cache
+                .reusable_vectors(Uuid::new_v4(), &with_simhash("a", 0))
+                .is_none()
+        );
+        assert!(
+            cache
+                .reusable_vectors(Uuid::new_v4(), &with_simhash("c", 0))
+                .is_some()
+        );
+        assert_eq!(cache.entries.len(), 2);
+        assert_eq!(cache.hashes.len(), 2);
+    }
+
+    #[test]
+    fn short_and_empty_content_fingerprint() {
+        assert_eq!(fingerprint("").simhash, 0);
+        assert_eq!(fingerprint("rust").simhash, fnv1a("rust"));
+        assert_eq!(
+            fingerprint("rust go").simhash,
+            fingerprint("Rust, Go!").simhash
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use crate::error::WorkerError;
+
+pub use shared_runtime::dlq::dead_letter;
+
+/// Pipeline stage at which a message failed, recorded in the `dlq.stage` header
+#[derive(Debug, Clone, Copy)]
+pub enum Stage {
+    Deserialize,
+    Vectorize,
+    Publish,
+}
+
+impl Stage {
+    /// The stage a processing error originated from
+    pub fn of(error: &WorkerError) -> Self {
+        match error {
+            WorkerError::KafkaError(_)
+            | WorkerError::MessageTooLarge(_)
+            | WorkerError::SerializationError(_) => Stage::Publish,
+            _ => Stage::Vectorize,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Deserialize => "deserialize",
+            Stage::Vectorize => "vectorize",
+            Stage::Publish => "publish",
+        }
+    }
+}
@@ -1,20 +1,27 @@
+use rdkafka::types::RDKafkaErrorCode;
+use shared_runtime::retry::Retryable;
 use std::fmt;
 
 /// Custom error types for the vector worker
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum WorkerError {
+    InvalidConfig(String),
     ModelInitError(String),
     InferenceError(String),
     KafkaError(String),
+    /// An event cannot fit in a Kafka message, however often it is retried
+    MessageTooLarge(String),
     SerializationError(String),
 }
 
 impl fmt::Display for WorkerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            WorkerError::InvalidConfig(e) => write!(f, "Configuration error: {}", e),
             WorkerError::ModelInitError(e) => write!(f, "Model initialization error: {}", e),
             WorkerError::InferenceError(e) => write!(f, "Inference error: {}", e),
             WorkerError::KafkaError(e) => write!(f, "Kafka error: {}", e),
+            WorkerError::MessageTooLarge(e) => write!(f, "Message too large: {}", e),
             WorkerError::SerializationError(e) => write!(f, "Serialization error: {}", e),
         }
     }
@@ -22,9 +29,21 @@ impl fmt::Display for WorkerError {
 
 impl std::error::Error for WorkerError {}
 
+impl Retryable for WorkerError {
+    /// Broker hiccups are transient; bad input and model failures are not
+    fn is_retryable(&self) -> bool {
+        matches!(self, WorkerError::KafkaError(_))
+    }
+}
+
 impl From<rdkafka::error::KafkaError> for WorkerError {
     fn from(err: rdkafka::error::KafkaError) -> Self {
-        WorkerError::KafkaError(err.to_string())
+        match err.rdkafka_error_code() {
+            Some(RDKafkaErrorCode::MessageSizeTooLarge) => {
+                WorkerError::MessageTooLarge(err.to_string())
+            }
+            _ => WorkerError::KafkaError(err.to_string()),
+        }
     }
 }
 
@@ -33,3 +52,12 @@ impl From<serde_json::Error> for WorkerError {
         WorkerError::SerializationError(err.to_string())
     }
 }
+
+impl From<shared_embedder::EmbedderError> for WorkerError {
+    fn from(err: shared_embedder::EmbedderError) -> Self {
+        match err {
+            shared_embedder::EmbedderError::ModelInitError(e) => WorkerError::ModelInitError(e),
+            shared_embedder::EmbedderError::InferenceError(e) => WorkerError::InferenceError(e),
+        }
+    }
+}
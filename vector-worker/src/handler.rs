@@ -1,50 +1,234 @@
-use crate::config::Config;
+use crate::chunker::{self, Chunk};
+use crate::config::{Config, DedupMode};
+use crate::dedup::{self, DedupCache};
 use crate::error::WorkerError;
-use crate::vectorizer::{generate_embedding, SharedModel};
+use crate::vectorizer::embed_documents;
 use rdkafka::producer::{FutureProducer, FutureRecord};
 use rdkafka::util::Timeout;
-use shared_types::{DocumentReceivedEvent, DocumentVectorizedEvent};
+use shared_embedder::{Bm25Encoder, SharedEmbedder};
+use shared_runtime::retry::{self, Failure};
+use shared_runtime::status;
+use shared_runtime::tombstone;
+use shared_types::{
+    ContentFingerprint, DenseVector, DocumentReceivedEvent, DocumentStage, DocumentStatusEvent,
+    DocumentVectorizedEvent, VectorChunk,
+};
 use std::time::Duration;
 use uuid::Uuid;
 
-/// Process a single document event
-/// 
-/// 1. Generates embedding using the ML model
-/// 2. Creates vectorized event
-/// 3. Publishes to output Kafka topic
-pub async fn process_document(
-    event: DocumentReceivedEvent,
-    model: SharedModel,
+/// Process a micro-batch of document events
+///
+/// 1. Splits every document into chunks and fingerprints its content
+/// 2. Reuses the vectors of recent exact duplicates, if configured
+/// 3. Generates embeddings for all other chunks of the batch in one model call,
+///    falling back to one call per document if the batched call fails
+/// 4. Creates one vectorized event per document, tagged with the model identity,
+///    carrying the text of every chunk and, if configured, linked to the
+///    document it duplicates and carrying a BM25 sparse vector per chunk
+/// 5. Splits events too large for one Kafka message into consecutive parts
+/// 6. Publishes them to the output Kafka topic concurrently, retrying transient failures
+///
+/// Returns one result per input event, in the same order. A document the
+/// model fails on reports the error without holding back the others.
+pub async fn process_batch(
+    events: Vec<DocumentReceivedEvent>,
+    model: SharedEmbedder,
+    sparse: Option<&Bm25Encoder>,
     producer: &FutureProducer,
     config: &Config,
-) -> Result<(), WorkerError> {
-    let doc_id = event.payload.id;
-    let content = event.payload.content;
+    cache: &mut DedupCache,
+) -> Vec<Result<(), Failure<WorkerError>>> {
+    let batch_size = events.len();
+    tracing::info!(batch_size, "Processing document batch");
 
-    tracing::info!(%doc_id, "Processing document");
+    // Split documents into chunks
+    let chunks: Vec<Vec<Chunk>> = events
+        .iter()
+        .map(|e| chunker::split(&e.payload.content, &config.chunking))
+        .collect();
+    let fingerprints: Vec<ContentFingerprint> = events
+        .iter()
+        .map(|e| dedup::fingerprint(&e.payload.content))
+        .collect();
 
-    // Generate embedding
-    let vector = generate_embedding(model, content).await?;
-    tracing::info!(%doc_id, dim = vector.len(), "Vector generated successfully");
+    // Normalised-equal content splits into the same words, so an exact
+    // duplicate's vectors fit chunk by chunk; the count guards the edge cases
+    let reused: Vec<Option<Vec<DenseVector>>> = events
+        .iter()
+        .zip(&fingerprints)
+        .zip(&chunks)
+        .map(|((event, fingerprint), spans)| {
+            cache
+                .reusable_vectors(event.payload.id, fingerprint)
+                .filter(|vectors| vectors.len() == spans.len())
+                .map(<[DenseVector]>::to_vec)
+        })
+        .collect();
 
-    // Publish vectorized event
-    publish_vectorized_event(producer, config, doc_id, vector).await?;
-    tracing::info!(%doc_id, "Published vectorized event");
+    let texts: Vec<Vec<String>> = events
+        .iter()
+        .zip(&chunks)
+        .zip(&reused)
+        .map(|((event, spans), reused)| match reused {
+            Some(_) => Vec::new(),
+            None => spans
+                .iter()
+                .map(|c| event.payload.content[c.start..c.end].to_string())
+                .collect(),
+        })
+        .collect();
+    let chunk_count: usize = texts.iter().map(Vec::len).sum();
 
-    Ok(())
+    // Generate embeddings
+    let model_id = model.model_id().to_string();
+    let embedded = embed_documents(&model, texts).await;
+    tracing::info!(batch_size, chunk_count, "Vectors generated successfully");
+
+    // Build vectorized events in batch order, so a duplicate within the
+    // batch is linked to the document before it
+    let mut output_events = Vec::with_capacity(batch_size);
+    for ((((event, spans), fingerprint), reused), embedded) in events
+        .into_iter()
+        .zip(chunks)
+        .zip(fingerprints)
+        .zip(reused)
+        .zip(embedded)
+    {
+        let doc_id = event.payload.id;
+        let doc_vectors: Vec<DenseVector> = match (reused, embedded) {
+            (Some(reused), _) => {
+                tracing::info!(%doc_id, "Reusing vectors of an exact duplicate");
+                reused
+            }
+            (None, Ok(vectors)) => vectors,
+            (None, Err(e)) => {
+                tracing::error!(%doc_id, error = %e, "Failed to generate vectors");
+                output_events.push(Err(e));
+                continue;
+            }
+        };
+
+        let duplicate_of = match config.dedup.mode {
+            DedupMode::Link => cache.find(doc_id, &fingerprint),
+            _ => None,
+        };
+        if let Some(link) = &duplicate_of {
+            tracing::info!(%doc_id, duplicate_of = %link.id, kind = link.kind.as_str(), "Linked duplicate document");
+        }
+        cache.remember(doc_id, &fingerprint, &doc_vectors);
+
+        let chunk_count = spans.len();
+        output_events.push(Ok(DocumentVectorizedEvent {
+            id: doc_id,
+            chunk_count,
+            chunks: spans
+                .into_iter()
+                .zip(doc_vectors)
+                .enumerate()
+                .map(|(index, (span, vector))| {
+                    let text = &event.payload.content[span.start..span.end];
+                    VectorChunk {
+                        index,
+                        start: span.start,
+                        end: span.end,
+                        vector,
+                        sparse: sparse.map(|encoder| encoder.encode_document(text)),
+                        text: Some(text.to_string()),
+                    }
+                })
+                .collect(),
+            metadata: event.payload.metadata,
+            model_id: model_id.clone(),
+            version: event.payload.version.unwrap_or_default(),
+            fingerprint: Some(fingerprint),
+            duplicate_of,
+        }));
+    }
+
+    // Publish vectorized events
+    let publishes = output_events.into_iter().map(|output_event| async move {
+        let output_event = output_event.map_err(Failure::once)?;
+        let (doc_id, version) = (output_event.id, output_event.version);
+        let parts = split_event(output_event, config.message_max_bytes).map_err(Failure::once)?;
+        let part_count = parts.len();
+        // Parts go out one after another, so they reach the partition in order
+        for part in &parts {
+            retry::with_backoff(&config.retry, || {
+                publish_vectorized_event(producer, config, part)
+            })
+            .await?;
+        }
+        tracing::info!(
+            %doc_id,
+            chunks = parts.iter().map(|part| part.chunks.len()).sum::<usize>(),
+            parts = part_count,
+            "Published vectorized event"
+        );
+        let vectorized = DocumentStatusEvent::now(doc_id, DocumentStage::Vectorized, Some(version));
+        status::publish(producer, &config.status_topic, vectorized);
+        Ok(())
+    });
+
+    futures::future::join_all(publishes).await
+}
+
+/// Room a Kafka record needs beside its payload, for the key, headers and framing
+const RECORD_OVERHEAD_BYTES: usize = 1024;
+
+/// Split a vectorized event into parts that each fit in one Kafka message
+///
+/// Chunks are packed in document order, so every part carries a consecutive
+/// run of them and the same document fields; the indexing worker recognises
+/// the last part by its final chunk index. An event that fits is returned
+/// whole. A single chunk too large for a message cannot be sent at all.
+fn split_event(
+    mut event: DocumentVectorizedEvent,
+    max_bytes: usize,
+) -> Result<Vec<DocumentVectorizedEvent>, WorkerError> {
+    let chunks = std::mem::take(&mut event.chunks);
+    let envelope = serde_json::to_vec(&event)?.len();
+    let budget = max_bytes.saturating_sub(envelope + RECORD_OVERHEAD_BYTES);
+
+    let mut parts = Vec::new();
+    let mut part = Vec::new();
+    let mut part_bytes = 0;
+    for chunk in chunks {
+        // Each chunk costs its encoding plus the separating comma
+        let chunk_bytes = serde_json::to_vec(&chunk)?.len() + 1;
+        if chunk_bytes > budget {
+            return Err(WorkerError::MessageTooLarge(format!(
+                "chunk {} of document {} needs {} bytes, more than the {} left in a message",
+                chunk.index, event.id, chunk_bytes, budget
+            )));
+        }
+        if part_bytes + chunk_bytes > budget {
+            parts.push(std::mem::take(&mut part));
+            part_bytes = 0;
+        }
+        part.push(chunk);
+        part_bytes += chunk_bytes;
+    }
+    if !part.is_empty() || parts.is_empty() {
+        parts.push(part);
+    }
+
+    Ok(parts
+        .into_iter()
+        .map(|chunks| DocumentVectorizedEvent {
+            chunks,
+            ..event.clone()
+        })
+        .collect())
 }
 
 /// Publish a vectorized document event to Kafka
 async fn publish_vectorized_event(
     producer: &FutureProducer,
     config: &Config,
-    doc_id: Uuid,
-    vector: Vec<f32>,
+    output_event: &DocumentVectorizedEvent,
 ) -> Result<(), WorkerError> {
-    let output_event = DocumentVectorizedEvent { id: doc_id, vector };
-
-    let payload = serde_json::to_vec(&output_event)?;
-    let doc_id_str = doc_id.to_string();
+    let payload = serde_json::to_vec(output_event)?;
+    let doc_id_str = output_event.id.to_string();
 
     let record = FutureRecord::to(&config.output_topic)
         .key(&doc_id_str)
@@ -52,10 +236,103 @@ async fn publish_vectorized_event(
 
     let timeout = Timeout::After(Duration::from_secs(config.kafka_send_timeout_secs));
 
+    producer
+        .send(record, timeout)
+        .await
+        .map_err(|(e, _)| WorkerError::from(e))?;
+
+    Ok(())
+}
+
+/// Forward a document deletion to the output topic
+///
+/// Like the incoming one, the tombstone is keyed by the document ID and has
+/// no payload, so it stays ordered with the document's vectorized events. Its
+/// version header is carried over when present.
+pub async fn forward_deletion(
+    producer: &FutureProducer,
+    config: &Config,
+    doc_id: Uuid,
+    version: Option<u64>,
+) -> Result<(), WorkerError> {
+    let doc_id_str = doc_id.to_string();
+
+    let mut record = FutureRecord::<str, [u8]>::to(&config.output_topic).key(&doc_id_str);
+    if let Some(version) = version {
+        record = record.headers(tombstone::headers(version));
+    }
+
+    let timeout = Timeout::After(Duration::from_secs(config.kafka_send_timeout_secs));
+
     producer
         .send(record, timeout)
         .await
         .map_err(|(e, _)| WorkerError::KafkaError(e.to_string()))?;
 
+    tracing::info!(%doc_id, "Forwarded deletion");
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_runtime::retry::Retryable;
+    use shared_types::DocumentMetadata;
+
+    fn event(chunks: usize, dims: usize) -> DocumentVectorizedEvent {
+        DocumentVectorizedEvent {
+            id: Uuid::new_v4(),
+            chunks: (0..chunks)
+                .map(|index| VectorChunk {
+                    index,
+                    start: index * 10,
+                    end: index * 10 + 10,
+                    vector: vec![0.123_456_78; dims],
+                    sparse: None,
+                    text: Some("0123456789".to_string()),
+                })
+                .collect(),
+            chunk_count: chunks,
+            metadata: DocumentMetadata::default(),
+            model_id: "model".to_string(),
+            version: 3,
+            fingerprint: None,
+            duplicate_of: None,
+        }
+    }
+
+    #[test]
+    fn small_events_are_sent_whole() {
+        let parts = split_event(event(4, 8), 1_000_000).unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].chunks.len(), 4);
+    }
+
+    #[test]
+    fn large_events_are_split_into_consecutive_parts_that_fit() {
+        let max_bytes = 64 * 1024;
+        let original = event(40, 384);
+        let parts = split_event(original.clone(), max_bytes).unwrap();
+
+        assert!(parts.len() > 1);
+        for part in &parts {
+            assert!(serde_json::to_vec(part).unwrap().len() + RECORD_OVERHEAD_BYTES <= max_bytes);
+            assert_eq!(part.id, original.id);
+            assert_eq!(part.version, 3);
+            assert_eq!(part.chunk_count, 40);
+        }
+        let indexes: Vec<usize> = parts
+            .iter()
+            .flat_map(|part| part.chunks.iter().map(|chunk| chunk.index))
+            .collect();
+        assert_eq!(indexes, (0..40).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn a_chunk_larger_than_a_message_is_permanent() {
+        let error = split_event(event(1, 384), 4 * 1024).unwrap_err();
+        assert!(matches!(error, WorkerError::MessageTooLarge(_)));
+        assert!(!error.is_retryable());
+    }
+}
@@ -1,27 +1,79 @@
 use crate::config::Config;
 use crate::error::WorkerError;
 use rdkafka::config::ClientConfig;
-use rdkafka::consumer::StreamConsumer;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::Message;
 use rdkafka::producer::FutureProducer;
+use rdkafka::{Offset, TopicPartitionList};
+use shared_runtime::retry::{DelayedRetryConfig, Relay};
+use std::collections::HashMap;
+use std::time::Duration;
 
 /// Create a Kafka consumer configured for the vector worker
-pub fn create_consumer(config: &Config) -> Result<StreamConsumer, WorkerError> {
+///
+/// Auto-commit is disabled: offsets are committed with [`commit_processed`]
+/// once messages have been fully handled, for at-least-once delivery.
+pub fn create_consumer(config: &Config, group_id: &str) -> Result<StreamConsumer, WorkerError> {
     let consumer: StreamConsumer = ClientConfig::new()
         .set("bootstrap.servers", &config.kafka_broker)
-        .set("group.id", &config.consumer_group)
-        .set("enable.auto.commit", "true")
+        .set("group.id", group_id)
+        .set("enable.auto.commit", "false")
         .set("auto.offset.reset", "earliest")
         .create()?;
 
     Ok(consumer)
 }
 
+/// Create the relay that feeds delayed retries back into the input topic
+///
+/// It consumes with its own consumer group (`<consumer_group>_retry`).
+pub fn create_relay(config: &Config, delayed: DelayedRetryConfig) -> Result<Relay, WorkerError> {
+    Ok(Relay {
+        consumer: create_consumer(config, &format!("{}_retry", config.consumer_group))?,
+        producer: create_producer(config)?,
+        delayed,
+        input_topic: config.input_topic.clone(),
+        send_timeout: Duration::from_secs(config.kafka_send_timeout_secs),
+    })
+}
+
 /// Create a Kafka producer for publishing vectorized events
 pub fn create_producer(config: &Config) -> Result<FutureProducer, WorkerError> {
     let producer: FutureProducer = ClientConfig::new()
         .set("bootstrap.servers", &config.kafka_broker)
         .set("message.timeout.ms", &config.kafka_timeout_ms)
+        .set("message.max.bytes", config.message_max_bytes.to_string())
         .create()?;
 
     Ok(producer)
 }
+
+/// Commit the offsets following the given messages, per partition
+///
+/// Call only once every message has been fully handled (published,
+/// dead-lettered or scheduled for retry), so a crash re-delivers them
+/// instead of losing them.
+pub fn commit_processed<'a, M: Message + 'a>(
+    consumer: &StreamConsumer,
+    messages: impl IntoIterator<Item = &'a M>,
+    mode: CommitMode,
+) -> Result<(), WorkerError> {
+    let mut next_offsets: HashMap<(String, i32), i64> = HashMap::new();
+    for message in messages {
+        let next = next_offsets
+            .entry((message.topic().to_string(), message.partition()))
+            .or_default();
+        *next = (*next).max(message.offset() + 1);
+    }
+    if next_offsets.is_empty() {
+        return Ok(());
+    }
+
+    let mut offsets = TopicPartitionList::new();
+    for ((topic, partition), offset) in next_offsets {
+        offsets.add_partition_offset(&topic, partition, Offset::Offset(offset))?;
+    }
+    consumer.commit(&offsets, mode)?;
+
+    Ok(())
+}
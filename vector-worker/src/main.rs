@@ -1,13 +1,27 @@
+mod chunker;
 mod config;
+mod dedup;
+mod dlq;
 mod error;
 mod handler;
 mod kafka;
 mod vectorizer;
 
 use config::Config;
-use rdkafka::consumer::Consumer;
-use rdkafka::message::Message;
+use dedup::DedupCache;
+use error::WorkerError;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::{Message, OwnedMessage};
+use rdkafka::producer::{FutureProducer, Producer};
+use rdkafka::util::Timeout;
+use shared_embedder::{Bm25Encoder, SharedEmbedder};
+use shared_runtime::retry::{self, Failure, Retryable};
+use shared_runtime::{Shutdown, status, tombstone};
 use shared_types::DocumentReceivedEvent;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+use uuid::Uuid;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -22,11 +36,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Config::default_config()
     });
 
+    chunker::validate(&config.chunking)?;
+    let sparse = config.sparse.map(Bm25Encoder::new).transpose()?;
+
     // Initialize ML model
-    let model = vectorizer::init_model().await?;
+    let model = vectorizer::init_model(&config.embedder, config.vector_dimension).await?;
 
     // Initialize Kafka consumer and producer
-    let consumer = kafka::create_consumer(&config)?;
+    let consumer = kafka::create_consumer(&config, &config.consumer_group)?;
     let producer = kafka::create_producer(&config)?;
 
     consumer.subscribe(&[&config.input_topic])?;
@@ -36,9 +53,214 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "Worker started. Listening for events..."
     );
 
-    // Main processing loop
+    let shutdown = Shutdown::listen();
+
+    // Delayed retries are fed back into the input topic by a separate relay
+    let relay = config
+        .retry
+        .delayed
+        .clone()
+        .map(|delayed| kafka::create_relay(&config, delayed))
+        .transpose()?
+        .map(|relay| {
+            let relay_shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                if let Err(e) = relay.run::<WorkerError>(relay_shutdown).await {
+                    tracing::error!(error = %e, "Delayed-retry relay stopped");
+                }
+            })
+        });
+
+    // Drain in-flight work after a signal, bounded by the shutdown deadline.
+    // An abandoned batch is not committed and will be re-delivered.
+    let draining = async {
+        run(
+            &consumer,
+            &producer,
+            &model,
+            sparse.as_ref(),
+            &config,
+            &shutdown,
+        )
+        .await?;
+        if let Some(relay) = relay {
+            let _ = relay.await;
+        }
+        Ok::<(), WorkerError>(())
+    };
+    tokio::select! {
+        result = draining => result?,
+        _ = shutdown.deadline(Duration::from_secs(config.shutdown_timeout_secs)) => {
+            tracing::warn!("Shutdown deadline exceeded, abandoning in-flight batch");
+        }
+    }
+
+    producer.flush(Timeout::After(Duration::from_secs(
+        config.kafka_send_timeout_secs,
+    )))?;
+    tracing::info!("Worker stopped");
+
+    Ok(())
+}
+
+/// Main processing loop
+///
+/// Returns once a shutdown signal has been received and the current batch
+/// has been handled and committed.
+async fn run(
+    consumer: &StreamConsumer,
+    producer: &FutureProducer,
+    model: &SharedEmbedder,
+    sparse: Option<&Bm25Encoder>,
+    config: &Config,
+    shutdown: &Shutdown,
+) -> Result<(), WorkerError> {
+    let mut cache = DedupCache::new(&config.dedup);
+
     loop {
-        match consumer.recv().await {
+        let messages = tokio::select! {
+            biased;
+            _ = shutdown.triggered() => return Ok(()),
+            messages = collect_batch(consumer, config) => messages,
+        };
+        if messages.is_empty() {
+            continue;
+        }
+
+        // Poison messages go straight to the dead-letter topic
+        let mut incoming = Vec::with_capacity(messages.len());
+        for message in &messages {
+            match parse_event(message) {
+                Ok(event) => incoming.push((event, message)),
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to deserialize message payload");
+                    handle_failure(
+                        producer,
+                        config,
+                        message,
+                        dlq::Stage::Deserialize,
+                        Failure::once(e),
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        // Deletions are forwarded as tombstones; documents are vectorized together
+        let mut events = Vec::with_capacity(incoming.len());
+        let mut sources = Vec::with_capacity(incoming.len());
+        for (event, message) in latest_per_document(incoming) {
+            match event {
+                Incoming::Document(event) => {
+                    events.push(event);
+                    sources.push(message);
+                }
+                Incoming::Deletion { doc_id, version } => {
+                    cache.forget(doc_id);
+                    let result = retry::with_backoff(&config.retry, || {
+                        handler::forward_deletion(producer, config, doc_id, version)
+                    })
+                    .await;
+                    if let Err(failure) = result {
+                        tracing::error!(%doc_id, error = %failure.error, "Failed to forward deletion");
+                        handle_failure(producer, config, message, dlq::Stage::Publish, failure)
+                            .await?;
+                    }
+                }
+            }
+        }
+
+        if !events.is_empty() {
+            let doc_ids: Vec<_> = events.iter().map(|e| e.payload.id).collect();
+            let results =
+                handler::process_batch(events, model.clone(), sparse, producer, config, &mut cache)
+                    .await;
+
+            for ((doc_id, message), result) in doc_ids.into_iter().zip(sources).zip(results) {
+                if let Err(failure) = result {
+                    tracing::error!(%doc_id, error = %failure.error, "Failed to process document");
+                    let stage = dlq::Stage::of(&failure.error);
+                    handle_failure(producer, config, message, stage, failure).await?;
+                }
+            }
+        }
+
+        // Every message of the batch is now published, dead-lettered or
+        // scheduled for retry, so its offset can be committed. The last
+        // commit before exiting is synchronous so it is not lost.
+        let mode = if shutdown.is_triggered() {
+            CommitMode::Sync
+        } else {
+            CommitMode::Async
+        };
+        kafka::commit_processed(consumer, &messages, mode)?;
+    }
+}
+
+/// Route a message whose processing failed
+///
+/// Transient failures go to the delayed-retry topic while it has attempts
+/// left; everything else is dead-lettered. Fails only if neither publish
+/// succeeds, so the worker stops instead of dropping the message.
+async fn handle_failure(
+    producer: &FutureProducer,
+    config: &Config,
+    message: &OwnedMessage,
+    stage: dlq::Stage,
+    failure: Failure<WorkerError>,
+) -> Result<(), WorkerError> {
+    if failure.error.is_retryable()
+        && retry::schedule_delayed::<WorkerError>(
+            producer,
+            &config.retry,
+            Duration::from_secs(config.kafka_send_timeout_secs),
+            message,
+        )
+        .await?
+    {
+        return Ok(());
+    }
+
+    let attempts = retry::total_attempts(&config.retry, message, failure.attempts);
+    let reason = failure.error.to_string();
+    dlq::dead_letter::<WorkerError>(
+        producer,
+        &config.dead_letter_topic,
+        Duration::from_secs(config.kafka_send_timeout_secs),
+        message,
+        stage.as_str(),
+        &reason,
+        attempts,
+    )
+    .await?;
+    status::publish_failed(
+        producer,
+        &config.status_topic,
+        message,
+        format!("{}: {}", stage.as_str(), reason),
+    );
+
+    Ok(())
+}
+
+/// Accumulate up to `batch_size` messages
+///
+/// Waits indefinitely for the first message, then keeps reading until the
+/// batch is full or `batch_linger_ms` has elapsed since the first message.
+async fn collect_batch(consumer: &StreamConsumer, config: &Config) -> Vec<OwnedMessage> {
+    let mut messages = Vec::with_capacity(config.batch_size);
+    let mut deadline = None;
+
+    while messages.len() < config.batch_size.max(1) {
+        let received = match deadline {
+            None => consumer.recv().await,
+            Some(deadline) => match tokio::time::timeout_at(deadline, consumer.recv()).await {
+                Ok(received) => received,
+                Err(_) => break,
+            },
+        };
+
+        match received {
             Err(e) => {
                 tracing::warn!(error = %e, "Kafka receive error");
             }
@@ -50,23 +272,119 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                 tracing::debug!(doc_id = %msg_key, "Received message");
 
-                if let Some(payload_bytes) = message.payload() {
-                    match serde_json::from_slice::<DocumentReceivedEvent>(payload_bytes) {
-                        Ok(event) => {
-                            let doc_id = event.payload.id;
-                            if let Err(e) =
-                                handler::process_document(event, model.clone(), &producer, &config)
-                                    .await
-                            {
-                                tracing::error!(%doc_id, error = %e, "Failed to process document");
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!(error = %e, "Failed to deserialize message payload");
-                        }
-                    }
-                }
+                messages.push(message.detach());
+            }
+        }
+
+        // The linger window starts with the first message of the batch
+        if deadline.is_none() && !messages.is_empty() {
+            deadline = Some(Instant::now() + Duration::from_millis(config.batch_linger_ms));
+        }
+    }
+
+    messages
+}
+
+/// What a message on the input topic asks the worker to do
+enum Incoming {
+    Document(DocumentReceivedEvent),
+    /// A tombstone: keyed by the document ID, without a payload, and
+    /// carrying the version it deletes up to in a header
+    Deletion {
+        doc_id: Uuid,
+        version: Option<u64>,
+    },
+}
+
+impl Incoming {
+    fn doc_id(&self) -> Uuid {
+        match self {
+            Incoming::Document(event) => event.payload.id,
+            Incoming::Deletion { doc_id, .. } => *doc_id,
+        }
+    }
+
+    /// Whether this event, received after `earlier`, makes it moot
+    ///
+    /// A later event wins, except that a submission never replaces one
+    /// carrying a newer version, nor a deletion of the same or a newer
+    /// version. Unversioned tombstones always win, as before versioning.
+    fn supersedes(&self, earlier: &Incoming) -> bool {
+        match (self, earlier) {
+            (Incoming::Document(later), Incoming::Document(earlier)) => {
+                later.payload.version >= earlier.payload.version
             }
+            (
+                Incoming::Document(later),
+                Incoming::Deletion {
+                    version: Some(deleted),
+                    ..
+                },
+            ) => later
+                .payload
+                .version
+                .is_some_and(|version| version > *deleted),
+            (
+                Incoming::Deletion {
+                    version: Some(deleted),
+                    ..
+                },
+                Incoming::Document(earlier),
+            ) => earlier
+                .payload
+                .version
+                .is_none_or(|version| version <= *deleted),
+            _ => true,
+        }
+    }
+}
+
+/// Deserialize the document event or deletion carried by a message
+fn parse_event(message: &OwnedMessage) -> Result<Incoming, WorkerError> {
+    let Some(payload_bytes) = message.payload() else {
+        let doc_id = message
+            .key_view::<str>()
+            .and_then(Result::ok)
+            .and_then(|key| Uuid::parse_str(key).ok())
+            .ok_or_else(|| {
+                WorkerError::SerializationError(
+                    "message has neither a payload nor a document ID key".to_string(),
+                )
+            })?;
+        return Ok(Incoming::Deletion {
+            doc_id,
+            version: tombstone::version(message),
+        });
+    };
+
+    Ok(Incoming::Document(serde_json::from_slice(payload_bytes)?))
+}
+
+/// Keep only the latest event of each document within a batch
+///
+/// A deletion makes an earlier submission of the same document moot, and a
+/// re-submission replaces an earlier deletion or older version, so
+/// superseded events are skipped rather than processed out of order.
+fn latest_per_document(
+    incoming: Vec<(Incoming, &OwnedMessage)>,
+) -> impl Iterator<Item = (Incoming, &OwnedMessage)> {
+    let mut latest: HashMap<Uuid, usize> = HashMap::new();
+    for (position, (event, _)) in incoming.iter().enumerate() {
+        let winner = latest.entry(event.doc_id()).or_insert(position);
+        if event.supersedes(&incoming[*winner].0) {
+            *winner = position;
         }
     }
+
+    incoming
+        .into_iter()
+        .enumerate()
+        .filter_map(move |(position, (event, message))| {
+            if latest[&event.doc_id()] == position {
+                Some((event, message))
+            } else {
+                tracing::debug!(doc_id = %event.doc_id(), "Skipping superseded event");
+                None
+            }
+        })
 }
@@ -1,52 +1,179 @@
 use crate::error::WorkerError;
-use rust_bert::pipelines::sentence_embeddings::{
-    SentenceEmbeddingsBuilder, SentenceEmbeddingsModel, SentenceEmbeddingsModelType,
-};
-use std::sync::{Arc, Mutex};
-
-/// Type alias for thread-safe model reference
-pub type SharedModel = Arc<Mutex<SentenceEmbeddingsModel>>;
-
-/// Initialize the sentence embeddings model
-/// 
-/// Loads the AllMiniLmL6V2 model which provides a good balance of:
-/// - Fast inference speed
-/// - Small memory footprint  
-/// - Good quality embeddings (384 dimensions)
-pub async fn init_model() -> Result<SharedModel, WorkerError> {
+use shared_embedder::{EmbedderConfig, SharedEmbedder};
+use shared_types::DenseVector;
+
+/// Initialize the sentence embedding backend selected in configuration
+///
+/// Fails fast if the model does not produce vectors of `expected_dimension`,
+/// which must match the size of the target Qdrant collection.
+pub async fn init_model(
+    config: &EmbedderConfig,
+    expected_dimension: usize,
+) -> Result<SharedEmbedder, WorkerError> {
     tracing::info!("Initializing ML model (this may take time on first run)...");
 
-    let model = tokio::task::spawn_blocking(|| {
-        SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL6V2)
-            .create_model()
-    })
-    .await
-    .map_err(|e| WorkerError::ModelInitError(e.to_string()))?
-    .map_err(|e| WorkerError::ModelInitError(e.to_string()))?;
+    let model = shared_embedder::load(config).await?;
+    shared_embedder::verify_dimension(model.as_ref(), expected_dimension)?;
 
     tracing::info!("ML model loaded successfully");
 
-    Ok(Arc::new(Mutex::new(model)))
+    Ok(model)
 }
 
-/// Generate embeddings for the given text
-/// 
+/// Generate embeddings for a batch of texts in a single model call
+///
 /// Runs inference in a blocking task to avoid blocking the async runtime.
-/// Returns a vector of f32 values representing the text embedding.
-pub async fn generate_embedding(
-    model: SharedModel,
-    text: String,
-) -> Result<Vec<f32>, WorkerError> {
-    let vector_result = tokio::task::spawn_blocking(move || {
-        let model_guard = model.lock().expect("Model mutex poisoned");
-        model_guard.encode(&[text])
-    })
-    .await
-    .map_err(|e| WorkerError::InferenceError(e.to_string()))?
-    .map_err(|e| WorkerError::InferenceError(e.to_string()))?;
-
-    vector_result
-        .into_iter()
-        .next()
-        .ok_or_else(|| WorkerError::InferenceError("No vector returned from model".to_string()))
+/// Returns one vector per input text, in the same order.
+pub async fn generate_embeddings(
+    model: SharedEmbedder,
+    texts: Vec<String>,
+) -> Result<Vec<DenseVector>, WorkerError> {
+    let expected = texts.len();
+    let vectors = shared_embedder::embed(&model, texts).await?;
+
+    if vectors.len() != expected {
+        return Err(WorkerError::InferenceError(format!(
+            "Model returned {} vectors for {} texts",
+            vectors.len(),
+            expected
+        )));
+    }
+
+    Ok(vectors)
+}
+
+/// Generate embeddings for the chunk texts of several documents
+///
+/// All chunks are embedded in one model call. If that call fails, every
+/// document is embedded on its own, so only the documents the model still
+/// fails on report an error. Returns one result per document, in order; a
+/// document without texts gets no vectors.
+pub async fn embed_documents(
+    model: &SharedEmbedder,
+    texts: Vec<Vec<String>>,
+) -> Vec<Result<Vec<DenseVector>, WorkerError>> {
+    let pending = texts.iter().filter(|texts| !texts.is_empty()).count();
+    if pending == 0 {
+        return texts.iter().map(|_| Ok(Vec::new())).collect();
+    }
+
+    let error = match generate_embeddings(model.clone(), texts.concat()).await {
+        Ok(vectors) => {
+            let mut vectors = vectors.into_iter();
+            return texts
+                .iter()
+                .map(|texts| Ok(vectors.by_ref().take(texts.len()).collect()))
+                .collect();
+        }
+        Err(e) => e,
+    };
+    // A single document would only fail the same way again
+    if pending == 1 {
+        return texts
+            .iter()
+            .map(|texts| {
+                if texts.is_empty() {
+                    Ok(Vec::new())
+                } else {
+                    Err(error.clone())
+                }
+            })
+            .collect();
+    }
+
+    tracing::warn!(
+        error = %error,
+        documents = pending,
+        "Batched inference failed, embedding documents one by one"
+    );
+    let mut results = Vec::with_capacity(texts.len());
+    for texts in texts {
+        results.push(if texts.is_empty() {
+            Ok(Vec::new())
+        } else {
+            generate_embeddings(model.clone(), texts).await
+        });
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_embedder::{Embedder, EmbedderError};
+    use std::sync::{Arc, Mutex};
+
+    /// Fails every call that contains a poisoned text, and records the size of each call
+    struct PickyEmbedder {
+        calls: Mutex<Vec<usize>>,
+    }
+
+    impl Embedder for PickyEmbedder {
+        fn embed(&self, texts: &[String]) -> Result<Vec<DenseVector>, EmbedderError> {
+            self.calls.lock().unwrap().push(texts.len());
+            if texts.iter().any(|text| text == "poison") {
+                return Err(EmbedderError::InferenceError("poisoned".to_string()));
+            }
+            Ok(texts.iter().map(|text| vec![text.len() as f32]).collect())
+        }
+
+        fn dimension(&self) -> usize {
+            1
+        }
+
+        fn model_id(&self) -> &str {
+            "picky"
+        }
+    }
+
+    fn texts(texts: &[&[&str]]) -> Vec<Vec<String>> {
+        texts
+            .iter()
+            .map(|texts| texts.iter().map(|text| text.to_string()).collect())
+            .collect()
+    }
+
+    fn model() -> (Arc<PickyEmbedder>, SharedEmbedder) {
+        let embedder = Arc::new(PickyEmbedder {
+            calls: Mutex::new(Vec::new()),
+        });
+        (embedder.clone(), embedder)
+    }
+
+    #[tokio::test]
+    async fn batch_is_embedded_in_one_call() {
+        let (embedder, model) = model();
+
+        let results = embed_documents(&model, texts(&[&["a", "bb"], &[], &["ccc"]])).await;
+
+        assert_eq!(results[0].as_ref().unwrap(), &vec![vec![1.0], vec![2.0]]);
+        assert!(results[1].as_ref().unwrap().is_empty());
+        assert_eq!(results[2].as_ref().unwrap(), &vec![vec![3.0]]);
+        assert_eq!(*embedder.calls.lock().unwrap(), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn failed_batch_falls_back_to_one_call_per_document() {
+        let (embedder, model) = model();
+
+        let results =
+            embed_documents(&model, texts(&[&["a"], &["b", "poison"], &[], &["cc"]])).await;
+
+        assert_eq!(results[0].as_ref().unwrap(), &vec![vec![1.0]]);
+        assert!(matches!(results[1], Err(WorkerError::InferenceError(_))));
+        assert!(results[2].as_ref().unwrap().is_empty());
+        assert_eq!(results[3].as_ref().unwrap(), &vec![vec![2.0]]);
+        assert_eq!(*embedder.calls.lock().unwrap(), vec![4, 1, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn single_failing_document_is_not_retried() {
+        let (embedder, model) = model();
+
+        let results = embed_documents(&model, texts(&[&[], &["poison"]])).await;
+
+        assert!(results[0].as_ref().unwrap().is_empty());
+        assert!(results[1].is_err());
+        assert_eq!(*embedder.calls.lock().unwrap(), vec![1]);
+    }
 }